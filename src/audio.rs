@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::path::Path;
+use raylib::prelude::*;
+use crate::caster::cast_ray;
+use crate::maze::Maze;
+use crate::player::Player;
+use crate::portals::Portals;
+use crate::sprites::normalize_angle;
+
+/// Qué tanto se separan los canales con el paneo (1.0 = un solo lado)
+const PAN_STRENGTH: f32 = 0.8;
+/// Segundos que dura el cruce entre dos pistas de música
+const CROSSFADE_SECS: f32 = 1.0;
+
+/// Cómo se oye un sonido ubicado en el mundo (distancias en píxeles del mundo)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpatialParams {
+    pub volume: f32,       // volumen a distancia mínima
+    pub min_distance: f32, // hasta aquí se oye a volumen completo
+    pub max_distance: f32, // desde aquí ya no se oye
+    pub occlusion: f32,    // factor de volumen con una pared en medio (1.0 = sin oclusión)
+}
+
+impl SpatialParams {
+    /// Valores por defecto para un nivel con celdas de `block_size` píxeles
+    pub fn new(block_size: f32) -> Self {
+        Self { volume: 1.0, min_distance: block_size, max_distance: 8.0 * block_size, occlusion: 0.4 }
+    }
+}
+
+/// Quién escucha: el jugador dentro del laberinto actual
+pub struct Listener<'b> {
+    pub maze: &'b Maze,
+    pub portals: &'b Portals,
+    pub player: &'b Player,
+    pub block_size: usize,
+}
+
+/// Volumen y paneo de un sonido en (x, y) oído por el jugador. El paneo usa la convención
+/// de raylib: 0.5 centro, 1.0 izquierda, 0.0 derecha.
+pub fn spatialize(
+    listener: &Listener,
+    (x, y): (f32, f32),
+    params: &SpatialParams,
+) -> (f32, f32) {
+    let player = listener.player;
+    let dx = x - player.position.x;
+    let dy = y - player.position.y;
+    let distance = dx.hypot(dy);
+
+    // Atenuación: completo hasta min_distance y cae cuadrático hasta max_distance
+    let range = (params.max_distance - params.min_distance).max(1.0);
+    let t = ((distance - params.min_distance) / range).clamp(0.0, 1.0);
+    let mut volume = params.volume * (1.0 - t) * (1.0 - t);
+    if volume <= 0.0 {
+        return (0.0, 0.5);
+    }
+
+    // Ángulo relativo a la vista: positivo es a la derecha de la pantalla
+    let angle = dy.atan2(dx);
+    let diff = normalize_angle(angle - player.angle);
+
+    // Oclusión: el rayo hacia la fuente choca con una pared, espejo o portal antes de llegar
+    if params.occlusion < 1.0 && distance > 1.0 {
+        let hit = cast_ray(listener.maze, listener.portals, player, angle, listener.block_size, None);
+        if hit.surface_distance < distance - 1.0 {
+            volume *= params.occlusion;
+        }
+    }
+
+    let pan = 0.5 - 0.5 * PAN_STRENGTH * diff.sin();
+    (volume, pan)
+}
+
+/// Una pista de música y qué tan fuerte suena dentro del cruce (0 a 1)
+struct Track<'a> {
+    music: Music<'a>,
+    fade: f32,
+    fading_in: bool,
+}
+
+/// Sonidos y música del juego con volúmenes por canal (maestro, música, efectos).
+/// Si no hay dispositivo de audio o falta un archivo, todo sigue funcionando en silencio.
+pub struct AudioManager<'a> {
+    device: Option<&'a RaylibAudio>,
+    sounds: HashMap<&'static str, Sound<'a>>,
+    tracks: HashMap<String, Track<'a>>, // por archivo, así dos nombres con el mismo archivo no se cruzan
+    music_paths: HashMap<&'static str, String>,
+    current_music: Option<String>,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+}
+
+impl<'a> AudioManager<'a> {
+    pub fn new(device: Option<&'a RaylibAudio>) -> Self {
+        if device.is_none() {
+            println!("[audio] sin dispositivo de audio, el juego sigue en silencio");
+        }
+        Self {
+            device,
+            sounds: HashMap::new(),
+            tracks: HashMap::new(),
+            music_paths: HashMap::new(),
+            current_music: None,
+            master_volume: 1.0,
+            music_volume: 0.6,
+            sfx_volume: 1.0,
+            muted: false,
+        }
+    }
+
+    /// Carga un efecto con nombre; si no se puede, ese nombre queda mudo
+    pub fn load_sound(&mut self, name: &'static str, path: &str) {
+        let Some(device) = self.device else { return };
+        match device.new_sound(path) {
+            Ok(sound) => { self.sounds.insert(name, sound); }
+            Err(e) => println!("[audio] no se pudo cargar {}: {}", path, e),
+        }
+    }
+
+    /// Registra una pista de música con nombre; usa el primer archivo de `paths` que exista
+    pub fn load_music(&mut self, name: &'static str, paths: &[&str]) {
+        let Some(device) = self.device else { return };
+        let Some(path) = paths.iter().find(|p| Path::new(p).exists()) else {
+            println!("[audio] no hay música para '{}'", name);
+            return;
+        };
+        if !self.tracks.contains_key(*path) {
+            match device.new_music(path) {
+                Ok(music) => { self.tracks.insert(path.to_string(), Track { music, fade: 0.0, fading_in: false }); }
+                Err(e) => {
+                    println!("[audio] no se pudo cargar {}: {}", path, e);
+                    return;
+                }
+            }
+        }
+        self.music_paths.insert(name, path.to_string());
+    }
+
+    fn sfx_gain(&self) -> f32 {
+        if self.muted { 0.0 } else { self.master_volume * self.sfx_volume }
+    }
+
+    fn music_gain(&self) -> f32 {
+        if self.muted { 0.0 } else { self.master_volume * self.music_volume }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        println!("[audio] {}", if self.muted { "silenciado" } else { "con sonido" });
+    }
+
+    /// Reproduce un efecto sin ubicación (centrado), por ejemplo al perder
+    pub fn play(&self, name: &str) {
+        self.play_with(name, 1.0, 0.5);
+    }
+
+    /// Reproduce un efecto ubicado en `at`
+    pub fn play_at(&self, name: &str, listener: &Listener, at: (f32, f32), params: &SpatialParams) {
+        if !self.sounds.contains_key(name) { return; }
+        let (volume, pan) = spatialize(listener, at, params);
+        if volume > 0.0 {
+            self.play_with(name, volume, pan);
+        }
+    }
+
+    fn play_with(&self, name: &str, volume: f32, pan: f32) {
+        let Some(sound) = self.sounds.get(name) else { return };
+        sound.set_volume(volume * self.sfx_gain());
+        sound.set_pan(pan);
+        sound.play();
+    }
+
+    pub fn stop(&self, name: &str) {
+        if let Some(sound) = self.sounds.get(name)
+            && sound.is_playing()
+        {
+            sound.stop();
+        }
+    }
+
+    /// Sonido en bucle que sigue a la fuente que más se oye de `sources`;
+    /// si ninguna se oye, lo detiene
+    pub fn update_loop(&self, name: &str, listener: &Listener, sources: &[(f32, f32, SpatialParams)]) {
+        let Some(sound) = self.sounds.get(name) else { return };
+        let loudest = sources.iter()
+            .map(|(x, y, params)| spatialize(listener, (*x, *y), params))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match loudest {
+            Some((volume, pan)) if volume > 0.0 => {
+                sound.set_volume(volume * self.sfx_gain());
+                sound.set_pan(pan);
+                if !sound.is_playing() {
+                    sound.play();
+                }
+            }
+            _ => self.stop(name),
+        }
+    }
+
+    /// Cambia a la pista con ese nombre cruzándola con la actual; si ya suena, no hace nada
+    pub fn play_music(&mut self, name: &str) {
+        let next = self.music_paths.get(name).cloned();
+        if next == self.current_music { return; }
+
+        if let Some(current) = self.current_music.as_ref().and_then(|path| self.tracks.get_mut(path)) {
+            current.fading_in = false;
+        }
+        if let Some(track) = next.as_ref().and_then(|path| self.tracks.get_mut(path)) {
+            if track.fade <= 0.0 {
+                track.music.play_stream(); // detenida vuelve a empezar desde el inicio
+            }
+            track.fading_in = true;
+        }
+        self.current_music = next;
+    }
+
+    /// Avanza los cruces y alimenta los streams; se llama una vez por frame
+    pub fn update(&mut self, dt: f32) {
+        let gain = self.music_gain();
+        let step = dt / CROSSFADE_SECS;
+        for track in self.tracks.values_mut() {
+            if track.fading_in {
+                track.fade = (track.fade + step).min(1.0);
+            } else if track.fade > 0.0 {
+                track.fade = (track.fade - step).max(0.0);
+                if track.fade <= 0.0 {
+                    track.music.stop_stream();
+                }
+            }
+            if track.fade > 0.0 {
+                track.music.set_volume(track.fade * gain);
+                track.music.update_stream();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: usize = 10;
+
+    // jugador en la celda (1, 2) mirando al este, con una pared en (4, 2)
+    fn maze() -> Maze {
+        ["+++++++++", "+       +", "+   +   +", "+       +", "+++++++++"]
+            .iter()
+            .map(|row| row.chars().collect())
+            .collect()
+    }
+
+    fn hear(at: (f32, f32), params: &SpatialParams) -> (f32, f32) {
+        let (maze, portals) = (maze(), Portals::new());
+        let player = Player::new(Vector2::new(15.0, 25.0), 0.0, 1.2);
+        let listener = Listener { maze: &maze, portals: &portals, player: &player, block_size: BLOCK };
+        spatialize(&listener, at, params)
+    }
+
+    fn params(occlusion: f32) -> SpatialParams {
+        SpatialParams { volume: 1.0, min_distance: 10.0, max_distance: 80.0, occlusion }
+    }
+
+    #[test]
+    fn attenuates_between_min_and_max_distance() {
+        assert_eq!(hear((20.0, 25.0), &params(1.0)).0, 1.0);
+        assert!((hear((60.0, 25.0), &params(1.0)).0 - 0.25).abs() < 1e-4);
+        assert_eq!(hear((15.0 + 80.0, 25.0), &params(1.0)), (0.0, 0.5));
+    }
+
+    #[test]
+    fn pans_by_side_of_the_view() {
+        let (_, ahead) = hear((30.0, 25.0), &params(1.0));
+        let (_, right) = hear((15.0, 35.0), &params(1.0));
+        let (_, left) = hear((15.0, 15.0), &params(1.0));
+        assert!((ahead - 0.5).abs() < 1e-4);
+        assert!((right - (0.5 - 0.5 * PAN_STRENGTH)).abs() < 1e-4);
+        assert!((left - (0.5 + 0.5 * PAN_STRENGTH)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn walls_in_between_muffle_the_sound() {
+        let (open, _) = hear((65.0, 25.0), &params(1.0));
+        let (muffled, _) = hear((65.0, 25.0), &params(0.4));
+        assert!((muffled - open * 0.4).abs() < 1e-4);
+        // antes de la pared no hay oclusión
+        assert_eq!(hear((35.0, 25.0), &params(0.4)).0, hear((35.0, 25.0), &params(1.0)).0);
+    }
+}
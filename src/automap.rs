@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use raylib::prelude::*;
+use crate::effects::EffectKind;
+use crate::entities::World;
+use crate::framebuffer::Framebuffer;
+use crate::maze::{Maze, is_walkable};
+use crate::minimap::{Fog, cell_to_color};
+use crate::player::Player;
+
+/// Píxeles por celda: al abrir, mínimo y máximo
+const DEFAULT_ZOOM: f32 = 28.0;
+const MIN_ZOOM: f32 = 8.0;
+const MAX_ZOOM: f32 = 96.0;
+/// Celdas por segundo al mover el mapa con las flechas
+const PAN_SPEED: f32 = 12.0;
+/// Cada cuántas celdas recorridas se agrega un punto al camino
+const TRAIL_SPACING: f32 = 0.25;
+const MAX_TRAIL_POINTS: usize = 20_000;
+const MAX_NOTE_LEN: usize = 24;
+const FLOOR_COLOR: Color = Color::new(30, 62, 72, 255);
+const TRAIL_COLOR: Color = Color::new(0, 218, 209, 170);
+const MARKER_COLOR: Color = Color::new(255, 70, 110, 255);
+
+/// Marcador puesto por el jugador, con una nota opcional (posición en celdas)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    pub x: f32,
+    pub y: f32,
+    pub note: String,
+}
+
+/// Moneda que el jugador llegó a ver y si ya la recogió
+#[derive(Clone, Copy, Debug)]
+struct KnownCoin {
+    x: f32,
+    y: f32,
+    collected: bool,
+}
+
+/// Mapa a pantalla completa (Tab): lo explorado, la salida, las monedas vistas, el camino
+/// recorrido y los marcadores del jugador. Se mueve y se acerca; todo en celdas.
+pub struct Automap {
+    pub open: bool,
+    center: (f32, f32),
+    zoom: f32,
+    follow: bool,        // centrado en el jugador hasta que se mueve a mano
+    trail: Vec<Vec<(f32, f32)>>,      // tramos del camino; se corta al cruzar un portal
+    last_at: Option<(f32, f32)>,      // posición del frame anterior
+    coins: HashMap<usize, KnownCoin>, // por índice de entidad
+    markers: Vec<Marker>,
+    note: Option<String>,             // nota que se está escribiendo
+    press: Option<(Vector2, bool)>,   // clic izquierdo apretado: dónde y si ya arrastró
+}
+
+impl Automap {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            center: (0.0, 0.0),
+            zoom: DEFAULT_ZOOM,
+            follow: true,
+            trail: Vec::new(),
+            last_at: None,
+            coins: HashMap::new(),
+            markers: Vec::new(),
+            note: None,
+            press: None,
+        }
+    }
+
+    /// Nivel nuevo: se olvida el camino, las monedas vistas y los marcadores
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.follow = true;
+        self.note = None;
+        self.press = None;
+    }
+
+    /// Escribiendo una nota: el teclado no mueve al jugador
+    pub fn typing(&self) -> bool {
+        self.open && self.note.is_some()
+    }
+
+    /// Se llama cada frame jugado: agrega el camino y recuerda las monedas que se ven
+    pub fn track(&mut self, player: &Player, world: &World, fog: &Fog, block_size: usize) {
+        let bs = block_size as f32;
+        let at = (player.position.x / bs, player.position.y / bs);
+        // un salto de más de una celda en un frame (portal) empieza otro tramo
+        let jumped = self.last_at.is_none_or(|last| (last.0 - at.0).hypot(last.1 - at.1) > 1.0);
+        self.last_at = Some(at);
+        if jumped { self.trail.push(Vec::new()); }
+        let points: usize = self.trail.iter().map(Vec::len).sum();
+        if let Some(segment) = self.trail.last_mut() {
+            let moved = segment.last().is_none_or(|last| (last.0 - at.0).hypot(last.1 - at.1) >= TRAIL_SPACING);
+            if moved && points < MAX_TRAIL_POINTS {
+                segment.push(at);
+            }
+        }
+        if self.follow { self.center = at; }
+
+        for (index, x, y, alive) in world.positions_of("coin") {
+            match self.coins.get_mut(&index) {
+                Some(coin) => coin.collected |= !alive,
+                None if alive && fog.is_seen((x as usize / block_size, y as usize / block_size)) => {
+                    self.coins.insert(index, KnownCoin { x: x / bs, y: y / bs, collected: false });
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Entrada con el mapa abierto; devuelve true si hay que cerrarlo
+    pub fn handle_input(&mut self, window: &mut RaylibHandle) -> bool {
+        let screen = (window.get_screen_width() as f32, window.get_screen_height() as f32);
+        let mouse = window.get_mouse_position();
+
+        // Escribiendo una nota: Enter la deja donde está el mouse, Esc la descarta
+        if let Some(note) = self.note.as_mut() {
+            while let Some(c) = window.get_char_pressed() {
+                if note.len() < MAX_NOTE_LEN && c.is_ascii() && !c.is_ascii_control() { note.push(c); }
+            }
+            if window.is_key_pressed(KeyboardKey::KEY_BACKSPACE) { note.pop(); }
+            if window.is_key_pressed(KeyboardKey::KEY_ENTER) || window.is_key_pressed(KeyboardKey::KEY_KP_ENTER) {
+                let (x, y) = self.to_world(mouse, screen);
+                let note = self.note.take().unwrap_or_default();
+                self.markers.push(Marker { x, y, note });
+            } else if window.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                self.note = None;
+            }
+            return false;
+        }
+
+        if window.is_key_pressed(KeyboardKey::KEY_TAB) || window.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return true;
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_N) {
+            self.note = Some(String::new());
+            while window.get_char_pressed().is_some() {} // la N no entra en la nota
+            return false;
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_C) { self.follow = true; }
+
+        // Mover con las flechas
+        let dt = window.get_frame_time();
+        let step = PAN_SPEED * dt * DEFAULT_ZOOM / self.zoom;
+        let mut pan = (0.0, 0.0);
+        if window.is_key_down(KeyboardKey::KEY_LEFT) { pan.0 -= step; }
+        if window.is_key_down(KeyboardKey::KEY_RIGHT) { pan.0 += step; }
+        if window.is_key_down(KeyboardKey::KEY_UP) { pan.1 -= step; }
+        if window.is_key_down(KeyboardKey::KEY_DOWN) { pan.1 += step; }
+        if pan != (0.0, 0.0) { self.pan(pan); }
+
+        // Zoom con la rueda (hacia el mouse) o con - / +
+        let mut zoom = window.get_mouse_wheel_move();
+        if window.is_key_pressed(KeyboardKey::KEY_EQUAL) || window.is_key_pressed(KeyboardKey::KEY_KP_ADD) { zoom += 1.0; }
+        if window.is_key_pressed(KeyboardKey::KEY_MINUS) || window.is_key_pressed(KeyboardKey::KEY_KP_SUBTRACT) { zoom -= 1.0; }
+        if zoom != 0.0 {
+            let before = self.to_world(mouse, screen);
+            self.zoom = (self.zoom * 1.2f32.powf(zoom)).clamp(MIN_ZOOM, MAX_ZOOM);
+            let after = self.to_world(mouse, screen);
+            self.pan((before.0 - after.0, before.1 - after.1));
+        }
+
+        // Clic izquierdo: arrastrar mueve el mapa, un clic sin arrastrar pone un marcador
+        if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.press = Some((mouse, false));
+        }
+        if let Some((start, dragged)) = self.press.as_mut() {
+            if window.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                let delta = window.get_mouse_delta();
+                if *dragged || (mouse.x - start.x).hypot(mouse.y - start.y) > 4.0 {
+                    *dragged = true;
+                    self.pan((-delta.x / self.zoom, -delta.y / self.zoom));
+                }
+            } else {
+                if !*dragged {
+                    let (x, y) = self.to_world(mouse, screen);
+                    self.markers.push(Marker { x, y, note: String::new() });
+                }
+                self.press = None;
+            }
+        }
+
+        // Clic derecho: borra el marcador más cercano al mouse
+        if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+            let nearest = self.markers.iter().enumerate()
+                .map(|(i, m)| {
+                    let (x, y) = self.to_screen((m.x, m.y), screen);
+                    (i, (x - mouse.x).hypot(y - mouse.y))
+                })
+                .filter(|(_, d)| *d < 14.0)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((i, _)) = nearest { self.markers.remove(i); }
+        }
+        false
+    }
+
+    fn pan(&mut self, (dx, dy): (f32, f32)) {
+        self.follow = false;
+        self.center = (self.center.0 + dx, self.center.1 + dy);
+    }
+
+    fn to_screen(&self, (x, y): (f32, f32), (w, h): (f32, f32)) -> (f32, f32) {
+        (w * 0.5 + (x - self.center.0) * self.zoom, h * 0.5 + (y - self.center.1) * self.zoom)
+    }
+
+    fn to_world(&self, point: Vector2, (w, h): (f32, f32)) -> (f32, f32) {
+        (self.center.0 + (point.x - w * 0.5) / self.zoom, self.center.1 + (point.y - h * 0.5) / self.zoom)
+    }
+
+    /// Dibuja el mapa sobre la vista oscurecida; `status` es (segundos restantes, juego en pausa)
+    pub fn draw(
+        &self,
+        (window, raylib_thread): (&mut RaylibHandle, &RaylibThread),
+        framebuffer: &Framebuffer,
+        (maze, fog, block_size): (&Maze, &Fog, usize),
+        player: &Player,
+        world: &World,
+        (time_left, paused): (u32, bool),
+    ) {
+        let mut renderer = window.begin_drawing(raylib_thread);
+        renderer.clear_background(Color::BLACK);
+        framebuffer.draw_to_screen(&mut renderer);
+        let screen_w = renderer.get_screen_width();
+        let screen_h = renderer.get_screen_height();
+        let screen = (screen_w as f32, screen_h as f32);
+        renderer.draw_rectangle(0, 0, screen_w, screen_h, Color::new(8, 10, 16, 235));
+
+        // celdas exploradas (todas mientras dura MapReveal)
+        let revealed = world.effects.is_active(EffectKind::MapReveal);
+        let size = self.zoom.ceil() as i32;
+        let pad = if self.zoom >= 16.0 { 1 } else { 0 };
+        let mut exits = Vec::new();
+        for (y, row) in maze.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if !revealed && !fog.is_seen((x, y)) { continue; }
+                let (sx, sy) = self.to_screen((x as f32, y as f32), screen);
+                if sx > screen.0 || sy > screen.1 || sx + self.zoom < 0.0 || sy + self.zoom < 0.0 { continue; }
+                let color = if cell == 'g' {
+                    exits.push((x, y));
+                    cell_to_color(cell)
+                } else if is_walkable(cell) {
+                    FLOOR_COLOR
+                } else {
+                    cell_to_color(cell)
+                };
+                renderer.draw_rectangle(sx as i32 + pad, sy as i32 + pad, size - 2 * pad, size - 2 * pad, color);
+            }
+        }
+
+        // camino recorrido
+        for pair in self.trail.iter().flat_map(|segment| segment.windows(2)) {
+            let (a, b) = (self.to_screen(pair[0], screen), self.to_screen(pair[1], screen));
+            renderer.draw_line_ex(Vector2::new(a.0, a.1), Vector2::new(b.0, b.1), 2.0, TRAIL_COLOR);
+        }
+
+        // monedas vistas: llenas si faltan, solo el borde si ya se recogieron
+        let coin_radius = (self.zoom * 0.18).max(3.0);
+        for coin in self.coins.values() {
+            let (x, y) = self.to_screen((coin.x, coin.y), screen);
+            if coin.collected {
+                renderer.draw_circle_lines(x as i32, y as i32, coin_radius, Color::GRAY);
+            } else {
+                renderer.draw_circle(x as i32, y as i32, coin_radius, Color::GOLD);
+            }
+        }
+
+        // salida
+        let exit_label = if world.exit_unlocked() { "SALIDA" } else { "SALIDA (cerrada)" };
+        let exit_color = if world.exit_unlocked() { Color::GREEN } else { Color::LIGHTGRAY };
+        for (x, y) in exits {
+            let (sx, sy) = self.to_screen((x as f32 + 0.5, y as f32), screen);
+            let w = renderer.measure_text(exit_label, 18);
+            renderer.draw_text(exit_label, sx as i32 - w / 2, sy as i32 - 22, 18, exit_color);
+        }
+
+        // marcadores
+        for (i, marker) in self.markers.iter().enumerate() {
+            let (x, y) = self.to_screen((marker.x, marker.y), screen);
+            renderer.draw_circle(x as i32, y as i32, 6.0, MARKER_COLOR);
+            renderer.draw_circle_lines(x as i32, y as i32, 6.0, Color::WHITE);
+            let label = if marker.note.is_empty() { format!("{}", i + 1) } else { format!("{} {}", i + 1, marker.note) };
+            renderer.draw_text(&label, x as i32 + 10, y as i32 - 8, 18, Color::RAYWHITE);
+        }
+
+        // jugador y hacia dónde mira
+        let bs = block_size as f32;
+        let (px, py) = self.to_screen((player.position.x / bs, player.position.y / bs), screen);
+        let tip = (px + player.angle.cos() * 14.0, py + player.angle.sin() * 14.0);
+        renderer.draw_line_ex(Vector2::new(px, py), Vector2::new(tip.0, tip.1), 3.0, Color::WHITE);
+        renderer.draw_circle(px as i32, py as i32, 6.0, Color::WHITE);
+        renderer.draw_circle(px as i32, py as i32, 4.0, Color::BLACK);
+
+        // encabezado y ayuda
+        let header = format!(
+            "MAPA   Tiempo {}:{:02}   Monedas {}/{}{}",
+            time_left / 60, time_left % 60, world.coins_collected, world.coins_total,
+            if paused { "   (en pausa)" } else { "" },
+        );
+        renderer.draw_text(&header, 24, 20, 28, Color::RAYWHITE);
+        let help = match &self.note {
+            Some(note) => format!("Nota: {}_   (Enter: dejar en el mouse, Esc: cancelar)", note),
+            None => "Tab/Esc cerrar | flechas o arrastrar: mover | rueda o +/-: zoom | clic: marcador | N: nota | clic der: borrar | C: centrar".to_string(),
+        };
+        renderer.draw_text(&help, 24, screen_h - 34, 18, Color::LIGHTGRAY);
+    }
+}
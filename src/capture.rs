@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+
+/// Carpeta de las capturas de pantalla (F2)
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Ruta para una captura nueva: `screenshots/shot-AAAAMMDD-HHMMSS-mmm[-hud].png` (hora UTC)
+pub fn screenshot_path(with_hud: bool) -> String {
+    let suffix = if with_hud { "-hud" } else { "" };
+    format!("{}/shot-{}{}.png", SCREENSHOT_DIR, timestamp(), suffix)
+}
+
+/// Guarda solo la vista renderizada (sin HUD), al tamaño del framebuffer
+pub fn save_screenshot(framebuffer: &Framebuffer) -> Result<String, String> {
+    let path = screenshot_path(false);
+    framebuffer.render_to_file(&path)?;
+    Ok(path)
+}
+
+/// Guarda lo que se dibujó en la ventana (con HUD y a tamaño de ventana). Se llama al final
+/// del dibujo del frame, antes del swap: después el back buffer ya no tiene ese frame.
+/// raylib escribe en la carpeta de trabajo con solo el nombre del archivo, así que después
+/// se mueve a `path`.
+pub fn save_window_screenshot(renderer: &mut RaylibDrawHandle, raylib_thread: &RaylibThread, path: &str) -> Result<(), String> {
+    let path = Path::new(path);
+    let name = path.file_name().ok_or(format!("Ruta inválida: {}", path.display()))?.to_string_lossy().to_string();
+    // abrir y cerrar un scissor vacía el lote de dibujo pendiente, así el HUD ya está en el buffer
+    let (width, height) = (renderer.get_screen_width(), renderer.get_screen_height());
+    drop(renderer.begin_scissor_mode(0, 0, width, height));
+    renderer.take_screenshot(raylib_thread, &name);
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("No se pudo crear {}: {}", dir.display(), e))?;
+    }
+    fs::rename(&name, path).map_err(|e| format!("No se pudo mover {} a {}: {}", name, path.display(), e))
+}
+
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = now.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}", year, month, day, time / 3600, time / 60 % 60, time % 60, now.subsec_millis())
+}
+
+/// Fecha (año, mes, día) de un día contado desde 1970-01-01 (algoritmo de Howard Hinnant)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Captura de una secuencia de frames (`--capture`): el juego avanza con un paso fijo
+/// y se guarda uno de cada `every` frames jugados como `frame_000001.png`, ...
+pub struct FrameCapture {
+    dir: String,
+    every: u32,
+    pub step: f32, // segundos simulados por frame
+    frame: u64,
+    saved: u32,
+}
+
+impl FrameCapture {
+    pub fn new(dir: &str, every: u32, fps: f32) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("No se pudo crear {}: {}", dir, e))?;
+        Ok(Self { dir: dir.to_string(), every: every.max(1), step: 1.0 / fps, frame: 0, saved: 0 })
+    }
+
+    /// Se llama una vez por frame jugado, con la vista ya renderizada
+    pub fn frame(&mut self, framebuffer: &Framebuffer) {
+        let due = self.frame.is_multiple_of(self.every as u64);
+        self.frame += 1;
+        if !due { return; }
+        self.saved += 1;
+        let path = Path::new(&self.dir).join(format!("frame_{:06}.png", self.saved));
+        if let Err(e) = framebuffer.render_to_file(&path.to_string_lossy()) {
+            println!("[capture] {}", e);
+        }
+    }
+
+    pub fn saved(&self) -> u32 {
+        self.saved
+    }
+}
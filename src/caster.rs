@@ -1,49 +1,87 @@
-use raylib::color::Color;
 use crate::framebuffer::Framebuffer;
 use crate::maze::{Maze, is_walkable};
 use crate::player::Player;
+use crate::portals::{Portals, MIRROR, PORTAL};
+use raylib::prelude::Color;
+use std::f32::consts::PI;
+use std::ops::Range;
+
+/// Lado de una celda: la cara que mira hacia el norte (y menor), sur, este (x mayor) u oeste
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Face {
+    North,
+    South,
+    East,
+    West,
+}
 
 /// Representa el resultado de la intersección de un rayo con el entorno.
 /// Da toda la información sobre el impacto, como distancia de choque,
 /// el tipo de pared impactada y la coordenada de textura para el mapeo.
 pub struct Intersect {
-    pub hit_distance: f32,
+    pub hit_distance: f32,      // largo total del recorrido, con reflejos y portales
+    pub surface_distance: f32,  // hasta la primera superficie (espejo o portal); tapa sprites detrás
     pub wall_type: char,
     pub texture_coord: f32,
+    pub cell: Option<(usize, usize)>, // celda (x, y) de la pared; None si el rayo salió del mapa
+    pub face: Face,                   // cara de esa celda que tocó el rayo
+    pub reflections: u32,             // espejos en los que rebotó (cada uno tiñe la pared)
 }
 
+/// Rebotes y saltos de portal que sigue un rayo antes de quedarse en la pared que toca
+pub const MAX_BOUNCES: u32 = 4;
+/// Tinte que agrega cada reflejo en un espejo (un poco más oscuro y azulado)
+pub const MIRROR_TINT: Color = Color::new(200, 220, 255, 255);
+
 /// Lanza un rayo desde la posición del jugador en un ángulo específico
-/// y devuelve información sobre la primera intersección con un objeto
+/// y devuelve información sobre la primera intersección con un objeto.
+/// Los espejos lo reflejan y los portales lo continúan desde su celda enlazada.
+/// Con `draw_line` se dibuja su recorrido en ese framebuffer (en píxeles del mundo, vista 2D);
+/// el render 3D en hilos pasa `None`.
 pub fn cast_ray(
-    framebuffer: &mut Framebuffer,
     maze: &Maze,
+    portals: &Portals,
     player: &Player,
     angle_rad: f32,
     block_size: usize,
-    draw_line: bool,
+    mut draw_line: Option<&mut Framebuffer>,
 ) -> Intersect {
-    // Longitud actual del rayo
+    // Longitud actual del tramo y lo recorrido en tramos anteriores
     let mut ray_length: f32 = 0.0;
+    let mut traveled: f32 = 0.0;
     // step para cada cuantos px tiramos rayo
     let ray_step_size: f32 = 1.0; 
 
-    framebuffer.set_current_color(Color::WHITESMOKE);
+    if let Some(framebuffer) = draw_line.as_deref_mut() {
+        framebuffer.set_current_color(Color::WHITESMOKE);
+    }
 
     let block_size_f32 = block_size as f32;
-    let mut prev_world_x = player.position.x;
-    let mut prev_world_y = player.position.y;
+    let (mut origin_x, mut origin_y) = (player.position.x, player.position.y);
+    let mut angle_rad = angle_rad;
+    let mut prev_world_x = origin_x;
+    let mut prev_world_y = origin_y;
+    let mut bounces = 0;              // espejos y portales, hasta MAX_BOUNCES
+    let mut reflections = 0;
+    let mut surface_distance = None;  // distancia al primer espejo o portal
+    let mut inside_portal = None;     // celda portal por la que se acaba de salir
 
     loop {
         // Se determina la posicion del rayo en el mundo
-        let world_x = player.position.x + ray_length * angle_rad.cos();
-        let world_y = player.position.y + ray_length * angle_rad.sin();
+        let world_x = origin_x + ray_length * angle_rad.cos();
+        let world_y = origin_y + ray_length * angle_rad.sin();
+        let distance = traveled + ray_length;
 
         // Verificar si el rayo ha salido de los límites del mundo
         if world_x < 0.0 || world_y < 0.0 {
             return Intersect { 
-                hit_distance: ray_length, 
+                hit_distance: distance, 
+                surface_distance: surface_distance.unwrap_or(distance),
                 wall_type: '#', 
-                texture_coord: 0.0 
+                texture_coord: 0.0,
+                cell: None,
+                face: Face::North,
+                reflections,
             };
         }
 
@@ -57,12 +95,25 @@ pub fn cast_ray(
         // Verificar si estamos fuera de los límites del laberinto
         if grid_y >= maze.len() || grid_x >= maze[grid_y].len() {
             return Intersect { 
-                hit_distance: ray_length, 
+                hit_distance: distance, 
+                surface_distance: surface_distance.unwrap_or(distance),
                 wall_type: '#', 
-                texture_coord: 0.0 
+                texture_coord: 0.0,
+                cell: None,
+                face: Face::North,
+                reflections,
             };
         }
 
+        // Al salir de un portal se atraviesa su celda hasta dejarla
+        if inside_portal == Some((grid_x, grid_y)) {
+            prev_world_x = world_x;
+            prev_world_y = world_y;
+            ray_length += ray_step_size;
+            continue;
+        }
+        inside_portal = None;
+
         let cell_type = maze[grid_y][grid_x];
         if !is_walkable(cell_type) {
             // Obtenemos la celda anterior (desde donde veníamos)
@@ -74,36 +125,90 @@ pub fn cast_ray(
             let local_x = world_x - cell_origin_x;
             let local_y = world_y - cell_origin_y;
 
-            // Se determina si estamos en un borde
-            let texture_coord = if grid_x != prev_grid_x && grid_y == prev_grid_y {
-                (local_y / block_size_f32).fract()
+            // Se determina si estamos en un borde vertical (cara este/oeste) u horizontal
+            let vertical_edge = if grid_x != prev_grid_x && grid_y == prev_grid_y {
+                true
             } else if grid_y != prev_grid_y && grid_x == prev_grid_x {
-                (local_x / block_size_f32).fract()
+                false
             } else {
                 let edge_x = local_x.min(block_size_f32 - local_x);
                 let edge_y = local_y.min(block_size_f32 - local_y);
-                if edge_x < edge_y { 
-                    (local_y / block_size_f32).fract() 
-                } else { 
-                    (local_x / block_size_f32).fract() 
+                edge_x < edge_y
+            };
+
+            if bounces < MAX_BOUNCES {
+                // Espejo: el tramo sigue desde el último punto libre con el ángulo reflejado
+                if cell_type == MIRROR {
+                    angle_rad = if vertical_edge { PI - angle_rad } else { -angle_rad };
+                    surface_distance.get_or_insert(distance);
+                    traveled = distance;
+                    (origin_x, origin_y) = (prev_world_x, prev_world_y);
+                    ray_length = ray_step_size;
+                    bounces += 1;
+                    reflections += 1;
+                    continue;
+                }
+                // Portal enlazado: el tramo sigue desde la celda del otro lado, girado
+                if cell_type == PORTAL
+                    && let Some(((x, y), angle)) = portals.transform((grid_x, grid_y), (world_x, world_y), angle_rad, block_size)
+                {
+                    surface_distance.get_or_insert(distance);
+                    traveled = distance;
+                    (origin_x, origin_y) = (x, y);
+                    (prev_world_x, prev_world_y) = (x, y);
+                    angle_rad = angle;
+                    inside_portal = portals.link((grid_x, grid_y)).map(|link| link.target);
+                    ray_length = 0.0;
+                    bounces += 1;
+                    continue;
                 }
+            }
+
+            let (texture_coord, face) = if vertical_edge {
+                let face = if local_x < block_size_f32 * 0.5 { Face::West } else { Face::East };
+                ((local_y / block_size_f32).fract(), face)
+            } else {
+                let face = if local_y < block_size_f32 * 0.5 { Face::North } else { Face::South };
+                ((local_x / block_size_f32).fract(), face)
             };
 
             return Intersect { 
-                hit_distance: ray_length, 
+                hit_distance: distance, 
+                surface_distance: surface_distance.unwrap_or(distance),
                 wall_type: cell_type, 
-                texture_coord 
+                texture_coord,
+                cell: Some((grid_x, grid_y)),
+                face,
+                reflections,
             };
         }
 
         // Dibujar el rayo si está habilitado
-        if draw_line {
+        if let Some(framebuffer) = draw_line.as_deref_mut() {
             framebuffer.set_pixel(pixel_x as u32, pixel_y as u32);
         }
-        
+
         // Actualizar posición anterior y avanzar el rayo
         prev_world_x = world_x;
         prev_world_y = world_y;
         ray_length += ray_step_size;
     }
-}
\ No newline at end of file
+}
+
+/// Ángulo del rayo de la columna `column` en una vista de `columns` columnas
+pub fn column_angle(player: &Player, column: u32, columns: u32) -> f32 {
+    player.angle - (player.field_of_view / 2.0) + (player.field_of_view * (column as f32 / columns as f32))
+}
+
+/// Lanza un rayo por cada columna de `columns` en una vista de `frame_width` columnas.
+/// Lo usan el render 3D (una franja por hilo) y la vista cenital de depuración.
+pub fn cast_view(
+    (maze, portals, block_size): (&Maze, &Portals, usize),
+    player: &Player,
+    columns: Range<u32>,
+    frame_width: u32,
+) -> Vec<Intersect> {
+    columns
+        .map(|i| cast_ray(maze, portals, player, column_angle(player, i, frame_width), block_size, None))
+        .collect()
+}
@@ -0,0 +1,157 @@
+use crate::settings::{MAX_FOV, MIN_FOV};
+
+/// Ayuda que se muestra con --help o cuando un argumento no es válido
+pub const USAGE: &str = "Uso: Raycasting [opciones]
+
+  --level <nombre|ruta>    empieza directo en un nivel (1, 2, maze1, level2 o un archivo .txt)
+  --seed <n>               semilla fija para colocar monedas/objetos y la IA
+  --resolution <AxB>       tamaño de la ventana, ej. 1280x720
+  --fullscreen             pantalla completa
+  --fov <grados>           campo de visión, de 45 a 110 (solo esta vez, no se guarda)
+  --no-audio               sin audio (no abre el dispositivo)
+  --record <archivo>       graba la partida para repetirla después
+  --replay <archivo>       repite una partida grabada (usa su nivel y semilla)
+  --screenshot-at <seg>    guarda una captura en screenshots/ a los <seg> segundos y cierra
+  --capture <carpeta>      guarda los frames jugados como secuencia PNG, con paso fijo
+  --capture-every <n>      con --capture, guarda uno de cada <n> frames (1 por defecto)
+  --capture-fps <fps>      con --capture, frames por segundo simulados (60 por defecto)
+  --help                   muestra esta ayuda";
+
+/// Argumentos de la línea de comandos; lo que no se pasa queda en None/false
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CliArgs {
+    pub level: Option<String>,
+    pub seed: Option<u64>,
+    pub resolution: Option<(i32, i32)>,
+    pub fullscreen: bool,
+    pub fov: Option<f32>,
+    pub no_audio: bool,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub screenshot_at: Option<f32>,
+    pub capture: Option<String>,
+    pub capture_every: Option<u32>,
+    pub capture_fps: Option<f32>,
+    pub help: bool,
+}
+
+impl CliArgs {
+    /// Lee los argumentos (sin el nombre del programa)
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // acepta tanto `--flag valor` como `--flag=valor`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = |name: &str| -> Result<String, String> {
+                inline.clone().or_else(|| args.next()).ok_or(format!("Falta el valor de {}", name))
+            };
+
+            match flag.as_str() {
+                "--level" => cli.level = Some(value("--level")?),
+                "--seed" => cli.seed = Some(parse_number(&value("--seed")?, "--seed")?),
+                "--resolution" => cli.resolution = Some(parse_resolution(&value("--resolution")?)?),
+                "--fullscreen" => cli.fullscreen = true,
+                "--fov" => {
+                    let fov: f32 = parse_number(&value("--fov")?, "--fov")?;
+                    if !(MIN_FOV..=MAX_FOV).contains(&fov) {
+                        return Err(format!("--fov fuera de rango ({} a {}): {}", MIN_FOV, MAX_FOV, fov));
+                    }
+                    cli.fov = Some(fov);
+                }
+                "--no-audio" => cli.no_audio = true,
+                "--record" => cli.record = Some(value("--record")?),
+                "--replay" => cli.replay = Some(value("--replay")?),
+                "--screenshot-at" => cli.screenshot_at = Some(parse_number(&value("--screenshot-at")?, "--screenshot-at")?),
+                "--capture" => cli.capture = Some(value("--capture")?),
+                "--capture-every" => {
+                    let every: u32 = parse_number(&value("--capture-every")?, "--capture-every")?;
+                    if every == 0 {
+                        return Err("--capture-every tiene que ser al menos 1".to_string());
+                    }
+                    cli.capture_every = Some(every);
+                }
+                "--capture-fps" => {
+                    let fps: f32 = parse_number(&value("--capture-fps")?, "--capture-fps")?;
+                    if !(1.0..=240.0).contains(&fps) {
+                        return Err(format!("--capture-fps fuera de rango (1 a 240): {}", fps));
+                    }
+                    cli.capture_fps = Some(fps);
+                }
+                "--help" | "-h" => cli.help = true,
+                _ => return Err(format!("Argumento desconocido: {}", arg)),
+            }
+        }
+        if cli.record.is_some() && cli.replay.is_some() {
+            return Err("--record y --replay no se pueden usar juntos".to_string());
+        }
+        if cli.capture.is_none() && (cli.capture_every.is_some() || cli.capture_fps.is_some()) {
+            return Err("--capture-every y --capture-fps van con --capture".to_string());
+        }
+        Ok(cli)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str, name: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("Valor inválido para {}: {}", name, text))
+}
+
+fn parse_resolution(text: &str) -> Result<(i32, i32), String> {
+    let (w, h) = text.split_once(['x', 'X']).ok_or(format!("Resolución inválida (usa AxB): {}", text))?;
+    let w: i32 = parse_number(w, "--resolution")?;
+    let h: i32 = parse_number(h, "--resolution")?;
+    if w < 320 || h < 240 {
+        return Err(format!("Resolución muy chica: {}x{}", w, h));
+    }
+    Ok((w, h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        CliArgs::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_separate_and_inline_values() {
+        let cli = parse(&["--level", "2", "--seed=42", "--resolution", "1280x720", "--fullscreen", "--no-audio"]).unwrap();
+        assert_eq!(cli.level.as_deref(), Some("2"));
+        assert_eq!(cli.seed, Some(42));
+        assert_eq!(cli.resolution, Some((1280, 720)));
+        assert!(cli.fullscreen && cli.no_audio);
+        assert_eq!(parse(&[]).unwrap(), CliArgs::default());
+    }
+
+    #[test]
+    fn fov_must_be_in_range() {
+        assert_eq!(parse(&["--fov", "90"]).unwrap().fov, Some(90.0));
+        assert_eq!(parse(&["--fov", &MIN_FOV.to_string()]).unwrap().fov, Some(MIN_FOV));
+        assert_eq!(parse(&["--fov", &MAX_FOV.to_string()]).unwrap().fov, Some(MAX_FOV));
+        assert!(parse(&["--fov", "10"]).is_err());
+        assert!(parse(&["--fov=200"]).is_err());
+        assert!(parse(&["--fov", "abc"]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_resolutions_and_missing_values() {
+        assert!(parse(&["--resolution", "100x100"]).is_err());
+        assert!(parse(&["--resolution", "1280"]).is_err());
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--nope"]).is_err());
+    }
+
+    #[test]
+    fn capture_options_are_validated() {
+        let cli = parse(&["--capture", "frames", "--capture-every", "2", "--capture-fps", "30"]).unwrap();
+        assert_eq!((cli.capture_every, cli.capture_fps), (Some(2), Some(30.0)));
+        assert!(parse(&["--capture", "frames", "--capture-every", "0"]).is_err());
+        assert!(parse(&["--capture", "frames", "--capture-fps", "500"]).is_err());
+        assert!(parse(&["--capture-every", "2"]).is_err());
+        assert!(parse(&["--record", "a.txt", "--replay", "b.txt"]).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use std::time::Duration;
+use raylib::prelude::*;
+use crate::entities::World;
+use crate::framebuffer::Framebuffer;
+use crate::maze::{Maze, is_walkable};
+use crate::player::Player;
+use crate::score::RunStats;
+use crate::settings::{Settings, MAX_FOV, MIN_FOV};
+use crate::textures::TextureManager;
+
+/// Líneas que guarda la salida de la consola
+const MAX_LOG: usize = 200;
+/// Comandos que recuerda el historial
+const MAX_HISTORY: usize = 50;
+const MAX_INPUT_LEN: usize = 80;
+const FONT_SIZE: i32 = 20;
+/// Alto de la consola desplegada, como fracción de la ventana
+const HEIGHT: f32 = 0.45;
+const TEXT_COLOR: Color = Color::new(220, 230, 240, 255);
+const ECHO_COLOR: Color = Color::new(0, 218, 209, 255);
+const ERROR_COLOR: Color = Color::new(255, 110, 110, 255);
+/// Comandos de la propia consola (no pasan por el registro)
+const BUILTINS: [&str; 2] = ["help", "clear"];
+
+/// Lo que un comando puede tocar del juego. Lo que main tiene que rehacer (cargar un nivel,
+/// reaplicar opciones) se pide con los campos de salida y main lo atiende al volver.
+pub struct Context<'a> {
+    pub player: &'a mut Player,
+    pub world: &'a mut World,
+    pub maze: &'a Maze,
+    pub block_size: usize,
+    pub settings: &'a mut Settings,
+    pub texman: &'a mut TextureManager,
+    pub level_total: &'a mut Duration, // tiempo total del nivel (con bonus)
+    pub level_elapsed: Duration,
+    pub run_stats: &'a mut RunStats,   // los trucos marcan la partida como hecha con trampa
+    pub level_request: Option<String>, // salida: nivel a cargar (`level`)
+    pub settings_changed: bool,        // salida: hay que reaplicar las opciones
+}
+
+/// Lo que hace un comando con sus argumentos; el texto del Ok (o del Err) se muestra en la consola
+pub type Handler = fn(&mut Context, &[&str]) -> Result<String, String>;
+
+/// Un comando registrado
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub args: &'static [&'static str], // palabras que completa Tab en el primer argumento
+    pub run: Handler,
+}
+
+/// Consola desplegable (tecla `): registro de comandos, historial y autocompletado.
+/// Los módulos agregan sus comandos con `register`.
+pub struct Console {
+    pub open: bool,
+    input: String,
+    commands: Vec<Command>,
+    history: Vec<String>,
+    browsing: Option<usize>, // posición en el historial mientras se recorre con las flechas
+    log: Vec<(String, Color)>,
+}
+
+impl Console {
+    /// Consola con los comandos de juego ya registrados
+    pub fn new() -> Self {
+        let mut console = Self {
+            open: false,
+            input: String::new(),
+            commands: Vec::new(),
+            history: Vec::new(),
+            browsing: None,
+            log: Vec::new(),
+        };
+        register_game_commands(&mut console);
+        console.print("Escribe help para ver los comandos", TEXT_COLOR);
+        console
+    }
+
+    /// Agrega un comando; uno con el mismo nombre reemplaza al anterior
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.browsing = None;
+    }
+
+    /// Muestra un error que no salió de un comando (ej. lo que main no pudo hacer después)
+    pub fn error(&mut self, text: &str) {
+        self.print(text, ERROR_COLOR);
+    }
+
+    fn print(&mut self, text: &str, color: Color) {
+        for line in text.lines() {
+            self.log.push((line.to_string(), color));
+        }
+        if self.log.len() > MAX_LOG {
+            self.log.drain(..self.log.len() - MAX_LOG);
+        }
+    }
+
+    /// Lee el teclado con la consola abierta; devuelve la línea a ejecutar al apretar Enter.
+    /// Esc o ` la cierran.
+    pub fn handle_input(&mut self, window: &mut RaylibHandle) -> Option<String> {
+        if window.is_key_pressed(KeyboardKey::KEY_ESCAPE) || window.is_key_pressed(KeyboardKey::KEY_GRAVE) {
+            self.toggle();
+            while window.get_char_pressed().is_some() {}
+            return None;
+        }
+        while let Some(c) = window.get_char_pressed() {
+            if self.input.len() < MAX_INPUT_LEN && c.is_ascii() && !c.is_ascii_control() && c != '`' {
+                self.input.push(c);
+            }
+        }
+        let pressed = |key| window.is_key_pressed(key) || window.is_key_pressed_repeat(key);
+        if pressed(KeyboardKey::KEY_BACKSPACE) { self.input.pop(); }
+        if pressed(KeyboardKey::KEY_TAB) { self.complete(); }
+        if pressed(KeyboardKey::KEY_UP) { self.browse(-1); }
+        if pressed(KeyboardKey::KEY_DOWN) { self.browse(1); }
+        if window.is_key_pressed(KeyboardKey::KEY_ENTER) || window.is_key_pressed(KeyboardKey::KEY_KP_ENTER) {
+            let line = std::mem::take(&mut self.input);
+            self.browsing = None;
+            return Some(line);
+        }
+        None
+    }
+
+    /// Recorre el historial: -1 más viejo, 1 más nuevo (pasado el último queda vacío)
+    fn browse(&mut self, step: i32) {
+        if self.history.is_empty() { return; }
+        let last = self.history.len() - 1;
+        self.browsing = match (self.browsing, step < 0) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) => (i < last).then_some(i + 1),
+        };
+        self.input = self.browsing.map(|i| self.history[i].clone()).unwrap_or_default();
+    }
+
+    /// Completa la palabra actual: el nombre del comando o su primer argumento. Si hay
+    /// varias opciones completa lo que tienen en común y, si no avanza, las muestra.
+    fn complete(&mut self) {
+        let words: Vec<&str> = self.input.split(' ').collect();
+        let candidates: Vec<&'static str> = match words.len() {
+            1 => BUILTINS.iter().copied().chain(self.commands.iter().map(|c| c.name)).collect(),
+            2 => self.commands.iter().find(|c| c.name == words[0]).map(|c| c.args.to_vec()).unwrap_or_default(),
+            _ => return,
+        };
+        let word = words[words.len() - 1];
+        let matches: Vec<&str> = candidates.into_iter().filter(|c| c.starts_with(word)).collect();
+        let Some(first) = matches.first() else { return };
+        let completed = if matches.len() == 1 {
+            format!("{} ", first)
+        } else {
+            let common = matches.iter().fold(first.len(), |len, m| {
+                first.bytes().zip(m.bytes()).take(len).take_while(|(a, b)| a == b).count()
+            });
+            first[..common].to_string()
+        };
+        if completed.len() <= word.len() {
+            let options = matches.join("  ");
+            self.print(&options, TEXT_COLOR);
+            return;
+        }
+        let prefix_len = self.input.len() - word.len();
+        self.input.truncate(prefix_len);
+        self.input.push_str(&completed);
+    }
+
+    /// Corre una línea: la guarda en el historial y muestra lo que devuelve el comando
+    pub fn execute(&mut self, line: &str, ctx: &mut Context) {
+        let line = line.trim();
+        if line.is_empty() { return; }
+        if self.history.last().map(|h| h.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > MAX_HISTORY { self.history.remove(0); }
+        }
+        self.print(&format!("> {}", line), ECHO_COLOR);
+        println!("[console] {}", line);
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = (words[0], &words[1..]);
+        let result = match name {
+            "help" => {
+                let lines: Vec<String> = self.commands.iter().map(|c| format!("{:<22} {}", c.usage, c.help)).collect();
+                Ok(lines.join("\n"))
+            }
+            "clear" => {
+                self.log.clear();
+                return;
+            }
+            _ => match self.commands.iter().find(|c| c.name == name) {
+                Some(command) => (command.run)(ctx, args),
+                None => Err(format!("Comando desconocido: {} (help para verlos)", name)),
+            },
+        };
+        match result {
+            Ok(text) => self.print(&text, TEXT_COLOR),
+            Err(e) => self.print(&e, ERROR_COLOR),
+        }
+    }
+
+    /// Dibuja el último frame subido con la consola desplegada encima
+    pub fn draw(&self, (window, raylib_thread): (&mut RaylibHandle, &RaylibThread), framebuffer: &Framebuffer) {
+        let mut d = window.begin_drawing(raylib_thread);
+        d.clear_background(Color::BLACK);
+        framebuffer.draw_to_screen(&mut d);
+
+        let width = d.get_screen_width();
+        let height = (d.get_screen_height() as f32 * HEIGHT) as i32;
+        let line_height = FONT_SIZE + 4;
+        d.draw_rectangle(0, 0, width, height, Color::new(10, 12, 20, 225));
+        d.draw_rectangle(0, height, width, 2, ECHO_COLOR);
+
+        // prompt abajo y la salida más nueva justo encima
+        let prompt_y = height - line_height - 8;
+        d.draw_text(&format!("> {}_", self.input), 12, prompt_y, FONT_SIZE, Color::RAYWHITE);
+        let visible = ((prompt_y - 8) / line_height).max(0) as usize;
+        for (index, (line, color)) in self.log.iter().rev().take(visible).enumerate() {
+            let y = prompt_y - (index as i32 + 1) * line_height;
+            d.draw_text(line, 12, y, FONT_SIZE, *color);
+        }
+    }
+}
+
+/// Comandos del juego: trucos, tiempo, monedas, teletransporte, nivel y FOV
+fn register_game_commands(console: &mut Console) {
+    console.register(Command {
+        name: "noclip",
+        usage: "noclip [on|off]",
+        help: "atravesar paredes",
+        args: &["on", "off"],
+        run: |ctx, args| {
+            ctx.player.noclip = switch(ctx.player.noclip, args, "uso: noclip [on|off]")?;
+            ctx.run_stats.cheated |= ctx.player.noclip;
+            Ok(format!("noclip {}", on_off(ctx.player.noclip)))
+        },
+    });
+    console.register(Command {
+        name: "god",
+        usage: "god [on|off]",
+        help: "ni la policia ni el tiempo hacen perder",
+        args: &["on", "off"],
+        run: |ctx, args| {
+            ctx.player.god = switch(ctx.player.god, args, "uso: god [on|off]")?;
+            ctx.run_stats.cheated |= ctx.player.god;
+            Ok(format!("god {}", on_off(ctx.player.god)))
+        },
+    });
+    console.register(Command {
+        name: "give",
+        usage: "give coins <n>",
+        help: "suma monedas (hasta el total del nivel)",
+        args: &["coins"],
+        run: |ctx, args| {
+            let ["coins", n] = args else { return Err("uso: give coins <n>".to_string()) };
+            let n: usize = n.parse().map_err(|_| format!("give: {} no es un numero", n))?;
+            ctx.world.coins_collected = (ctx.world.coins_collected + n).min(ctx.world.coins_total);
+            ctx.run_stats.cheated = true;
+            Ok(format!("Monedas: {}/{}", ctx.world.coins_collected, ctx.world.coins_total))
+        },
+    });
+    console.register(Command {
+        name: "time",
+        usage: "time <segundos>",
+        help: "deja ese tiempo restante en el nivel",
+        args: &[],
+        run: |ctx, args| {
+            let [seconds] = args else { return Err("uso: time <segundos>".to_string()) };
+            let seconds: f32 = seconds.parse().ok().filter(|s: &f32| s.is_finite() && *s >= 0.0)
+                .ok_or(format!("time: {} no es un tiempo valido", seconds))?;
+            *ctx.level_total = ctx.level_elapsed + Duration::from_secs_f32(seconds);
+            ctx.run_stats.cheated = true;
+            Ok(format!("Quedan {:.0} s", seconds))
+        },
+    });
+    console.register(Command {
+        name: "tp",
+        usage: "tp <x> <y>",
+        help: "lleva al centro de la celda (x, y)",
+        args: &[],
+        run: |ctx, args| {
+            let [x, y] = args else { return Err("uso: tp <x> <y>".to_string()) };
+            let (Ok(x), Ok(y)) = (x.parse::<usize>(), y.parse::<usize>()) else {
+                return Err("uso: tp <x> <y> (celdas, desde 0)".to_string());
+            };
+            let Some(&cell) = ctx.maze.get(y).and_then(|row| row.get(x)) else {
+                return Err(format!("tp: ({}, {}) esta fuera del mapa", x, y));
+            };
+            if !is_walkable(cell) && !ctx.player.noclip {
+                return Err(format!("tp: ({}, {}) es una pared '{}' (usa noclip)", x, y, cell));
+            }
+            let bs = ctx.block_size as f32;
+            ctx.player.position = Vector2::new((x as f32 + 0.5) * bs, (y as f32 + 0.5) * bs);
+            ctx.run_stats.cheated = true;
+            Ok(format!("En ({}, {})", x, y))
+        },
+    });
+    console.register(Command {
+        name: "level",
+        usage: "level <nombre>",
+        help: "carga un nivel (1, 2, maze1 o un archivo .txt)",
+        args: &["1", "2"],
+        run: |ctx, args| {
+            let [name] = args else { return Err("uso: level <nombre>".to_string()) };
+            ctx.level_request = Some(name.to_string());
+            Ok(format!("Cargando {}", name))
+        },
+    });
+    console.register(Command {
+        name: "fov",
+        usage: "fov <grados>",
+        help: "campo de vision (no se guarda en las opciones)",
+        args: &[],
+        run: |ctx, args| {
+            let [degrees] = args else { return Err("uso: fov <grados>".to_string()) };
+            let degrees: f32 = degrees.parse().map_err(|_| format!("fov: {} no es un numero", degrees))?;
+            ctx.settings.fov_override = Some(degrees.clamp(MIN_FOV, MAX_FOV));
+            ctx.settings_changed = true;
+            Ok(format!("FOV {:.0} grados", ctx.settings.fov()))
+        },
+    });
+}
+
+/// Sin argumento invierte el valor; si no, `on`/`off` (o 1/0)
+fn switch(current: bool, args: &[&str], usage: &str) -> Result<bool, String> {
+    match args {
+        [] => Ok(!current),
+        ["on" | "1"] => Ok(true),
+        ["off" | "0"] => Ok(false),
+        _ => Err(usage.to_string()),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
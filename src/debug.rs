@@ -0,0 +1,159 @@
+use std::time::Duration;
+use raylib::prelude::*;
+use crate::caster::{Face, Intersect, column_angle};
+use crate::framebuffer::Framebuffer;
+use crate::maze::Maze;
+use crate::minimap::{cell_to_color, draw_line};
+use crate::player::{Player, collision_points};
+use crate::sprites::{Sprite, FOV_CULL_MARGIN, normalize_angle};
+
+/// Margen alrededor del mapa en la vista cenital (px del framebuffer)
+const MARGIN: f32 = 12.0;
+const BACKGROUND: Color = Color::new(14, 16, 26, 255);
+const FLOOR: Color = Color::new(30, 36, 52, 255);
+const RAY_COLOR: Color = Color::new(120, 110, 50, 255);
+const BOUNCE_COLOR: Color = Color::new(255, 60, 255, 255); // el rayo siguió tras un espejo o portal
+const FOV_COLOR: Color = Color::WHITE;
+const CULL_COLOR: Color = Color::new(255, 150, 40, 255);
+const VISIBLE_SPRITE: Color = Color::new(90, 255, 120, 255);
+const CULLED_SPRITE: Color = Color::new(200, 60, 60, 255);
+
+/// Modos de depuración (F3 los recorre en orden)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugMode {
+    Off,
+    Timings, // vista normal con el panel de tiempos
+    TopDown, // vista cenital en lugar de la 3D, también con el panel
+}
+
+impl DebugMode {
+    pub fn next(self) -> Self {
+        match self {
+            DebugMode::Off => DebugMode::Timings,
+            DebugMode::Timings => DebugMode::TopDown,
+            DebugMode::TopDown => DebugMode::Off,
+        }
+    }
+}
+
+/// Tiempos de las etapas del frame. Rayos y sprites son los de la franja más lenta, que es
+/// la que marca cuánto tarda el render en hilos.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameTimings {
+    pub rays: Duration,
+    pub sprites: Duration,
+    pub minimap: Duration,
+    pub post: Duration,
+    pub upload: Duration, // del frame anterior: se sube después de armar el panel
+}
+
+impl FrameTimings {
+    /// Líneas del panel, con el total del frame para comparar
+    pub fn lines(&self, frame_time: f32) -> Vec<String> {
+        let ms = |d: Duration| d.as_secs_f32() * 1000.0;
+        vec![
+            format!("rayos     {:6.2} ms", ms(self.rays)),
+            format!("sprites   {:6.2} ms", ms(self.sprites)),
+            format!("minimapa  {:6.2} ms", ms(self.minimap)),
+            format!("post      {:6.2} ms", ms(self.post)),
+            format!("subida    {:6.2} ms", ms(self.upload)),
+            format!("frame     {:6.2} ms", frame_time * 1000.0),
+        ]
+    }
+}
+
+/// Vista cenital para depurar: el laberinto entero con los rayos de la vista, uno por
+/// columna tal como los lanzó el render (el punto de impacto
+/// lleva el color de la cara: N rojo, S verde, E azul, O amarillo), el radio de colisión
+/// con sus puntos de prueba, los sprites y el FOV (blanco) con el de culling (naranja).
+/// Los sprites fuera del culling salen en rojo.
+pub fn render_debug_view(
+    framebuffer: &mut Framebuffer,
+    (maze, block_size): (&Maze, usize),
+    player: &Player,
+    sprites: &[Sprite],
+    hits: &[Intersect],
+) {
+    let bs = block_size as f32;
+    let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+    let cols = maze.iter().map(|row| row.len()).max().unwrap_or(1) as f32;
+    let rows = maze.len().max(1) as f32;
+    // píxeles del framebuffer por píxel del mundo, para que entre todo el laberinto
+    let scale = ((width - 2.0 * MARGIN) / (cols * bs)).min((height - 2.0 * MARGIN) / (rows * bs)).max(0.01);
+    let origin = ((width - cols * bs * scale) * 0.5, (height - rows * bs * scale) * 0.5);
+    let to_screen = |x: f32, y: f32| (origin.0 + x * scale, origin.1 + y * scale);
+
+    framebuffer.set_current_color(BACKGROUND);
+    framebuffer.fill_rect(0, 0, framebuffer.width as i32, framebuffer.height as i32);
+    let cell = (bs * scale).max(1.0);
+    for (row_index, row) in maze.iter().enumerate() {
+        for (col_index, &c) in row.iter().enumerate() {
+            let (x, y) = to_screen(col_index as f32 * bs, row_index as f32 * bs);
+            framebuffer.set_current_color(if c == ' ' { FLOOR } else { cell_to_color(c) });
+            // 1px de separación para que se vea la grilla
+            framebuffer.fill_rect(x as i32, y as i32, (cell as i32 - 1).max(1), (cell as i32 - 1).max(1));
+        }
+    }
+
+    // rayos de la vista, hasta la primera superficie (espejo o portal si rebotó)
+    let player_at = to_screen(player.position.x, player.position.y);
+    for (i, hit) in hits.iter().enumerate() {
+        let angle = column_angle(player, i as u32, hits.len() as u32);
+        let d = hit.surface_distance;
+        let surface = to_screen(player.position.x + d * angle.cos(), player.position.y + d * angle.sin());
+        framebuffer.set_current_color(RAY_COLOR);
+        draw_line(framebuffer, player_at, surface);
+        let mark = if hit.surface_distance < hit.hit_distance {
+            BOUNCE_COLOR
+        } else {
+            match hit.face {
+                Face::North => Color::RED,
+                Face::South => Color::GREEN,
+                Face::East => Color::BLUE,
+                Face::West => Color::YELLOW,
+            }
+        };
+        framebuffer.set_current_color(mark);
+        framebuffer.fill_rect(surface.0 as i32 - 1, surface.1 as i32 - 1, 3, 3);
+    }
+
+    // FOV real y el de culling de sprites
+    let reach = 4.0 * bs;
+    let half_fov = player.field_of_view / 2.0;
+    for (half, color) in [(half_fov, FOV_COLOR), (half_fov * FOV_CULL_MARGIN, CULL_COLOR)] {
+        framebuffer.set_current_color(color);
+        for side in [-1.0, 1.0] {
+            let a = player.angle + side * half;
+            draw_line(framebuffer, player_at, to_screen(player.position.x + reach * a.cos(), player.position.y + reach * a.sin()));
+        }
+    }
+
+    // sprites: círculo de su tamaño, verde si pasa el culling
+    for sprite in sprites.iter().filter(|s| s.is_active) {
+        let diff = normalize_angle((sprite.world_y - player.position.y).atan2(sprite.world_x - player.position.x) - player.angle);
+        let visible = diff.abs() <= half_fov * FOV_CULL_MARGIN;
+        framebuffer.set_current_color(if visible { VISIBLE_SPRITE } else { CULLED_SPRITE });
+        draw_circle(framebuffer, to_screen(sprite.world_x, sprite.world_y), (sprite.size * 0.5 * scale).max(2.0));
+    }
+
+    // volumen de colisión del jugador y los puntos que se prueban contra las paredes
+    framebuffer.set_current_color(Color::WHITE);
+    draw_circle(framebuffer, player_at, (player.collision_radius * scale).max(2.0));
+    framebuffer.set_current_color(Color::ORANGE);
+    for point in collision_points(player.position, player.collision_radius) {
+        let (x, y) = to_screen(point.x, point.y);
+        framebuffer.fill_rect(x as i32 - 1, y as i32 - 1, 2, 2);
+    }
+}
+
+/// Contorno de un círculo con el color actual
+fn draw_circle(framebuffer: &mut Framebuffer, (cx, cy): (f32, f32), radius: f32) {
+    let steps = ((radius * 6.0) as i32).clamp(12, 256);
+    let point = |i: i32| {
+        let a = i as f32 / steps as f32 * std::f32::consts::TAU;
+        (cx + radius * a.cos(), cy + radius * a.sin())
+    };
+    for i in 0..steps {
+        draw_line(framebuffer, point(i), point(i + 1));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::PI;
+use raylib::prelude::Color;
+use crate::caster::Face;
+use crate::random;
+use crate::textures::{CpuTexture, TextureId, TextureStore};
+
+/// Probabilidad por defecto de que una cara de pared tenga graffiti
+pub const DEFAULT_DECAL_CHANCE: f32 = 0.55;
+/// Tags del jugador que se conservan; al pasar el tope se borra el más viejo
+const MAX_SPRAYS: usize = 64;
+/// Tamaño del tag del jugador, como fracción de la cara
+const SPRAY_SIZE: f32 = 0.45;
+/// Distancia máxima (en celdas) a la que el jugador alcanza a pintar
+pub const SPRAY_REACH: f32 = 1.5;
+
+/// Un graffiti sobre una cara de pared, en coordenadas de la cara (0 a 1)
+#[derive(Clone, Copy, Debug)]
+pub struct Decal {
+    pub texture: TextureId,
+    pub u: f32,     // borde izquierdo
+    pub v: f32,     // borde superior
+    pub size: f32,  // lado (es cuadrado)
+    pub flip: bool, // espejado horizontal
+}
+
+impl Decal {
+    /// Si cruza la columna `u` de la cara
+    pub fn covers_column(&self, u: f32) -> bool {
+        u >= self.u && u < self.u + self.size
+    }
+
+    /// Color de la pared en (u, v) con el decal encima, mezclado por su alpha.
+    /// `column_height` es el alto en pantalla de la pared, para elegir el mipmap.
+    pub fn paint(&self, store: &TextureStore, (u, v): (f32, f32), column_height: f32, under: Color) -> Color {
+        let lu = (u - self.u) / self.size;
+        let lv = (v - self.v) / self.size;
+        if !(0.0..1.0).contains(&lu) || !(0.0..1.0).contains(&lv) { return under; }
+        let lu = if self.flip { 1.0 - lu } else { lu };
+        let image = store.get(self.texture);
+        let lod = (image.height as f32 / (column_height * self.size).max(1.0)).log2();
+        let c = image.sample(lu, lv, lod, store.sampling());
+        let a = c.a as u16; let ai = 255u16 - a;
+        let mix = |top: u8, bottom: u8| ((top as u16 * a + bottom as u16 * ai) / 255) as u8;
+        Color::new(mix(c.r, under.r), mix(c.g, under.g), mix(c.b, under.b), 255)
+    }
+}
+
+/// Graffiti de las paredes: uno por cara elegido al azar de un pool (con semilla por nivel,
+/// así cada cara lo conserva entre frames) más los tags que pinta el jugador.
+pub struct Decals {
+    seed: u64,
+    pool: Vec<TextureId>,
+    chance: f32,
+    sprays: HashMap<(usize, usize, Face), Vec<Decal>>,
+    spray_order: VecDeque<(usize, usize, Face)>,
+    spray_texture: TextureId,
+}
+
+impl Decals {
+    pub fn new(store: &mut TextureStore) -> Self {
+        let spray_texture = match CpuTexture::load("assets/tag.png") {
+            Ok(texture) => texture,
+            Err(_) => generate_spray_tag(128, Color::new(255, 60, 200, 255)),
+        };
+        Self {
+            seed: 0,
+            pool: Vec::new(),
+            chance: DEFAULT_DECAL_CHANCE,
+            sprays: HashMap::new(),
+            spray_order: VecDeque::new(),
+            spray_texture: store.add(spray_texture),
+        }
+    }
+
+    /// Empieza un nivel: nueva semilla, pool y probabilidad, y sin tags del jugador
+    pub fn reset(&mut self, seed: u64, pool: Vec<TextureId>, chance: f32) {
+        self.seed = seed;
+        self.reseed(pool, chance);
+        self.sprays.clear();
+        self.spray_order.clear();
+    }
+
+    /// Cambia el pool y la probabilidad con la misma semilla (recarga de texturas): las caras
+    /// se reparten de nuevo entre las texturas nuevas y los tags del jugador se quedan
+    pub fn reseed(&mut self, pool: Vec<TextureId>, chance: f32) {
+        self.pool = pool;
+        self.chance = chance.clamp(0.0, 1.0);
+    }
+
+    /// Graffiti de una cara: el que le toca por semilla (si tiene) y los tags encima
+    pub fn on_face(&self, cell: (usize, usize), face: Face) -> impl Iterator<Item = Decal> + '_ {
+        self.seeded(cell, face)
+            .into_iter()
+            .chain(self.sprays.get(&(cell.0, cell.1, face)).into_iter().flatten().copied())
+    }
+
+    fn seeded(&self, (x, y): (usize, usize), face: Face) -> Option<Decal> {
+        if self.pool.is_empty() { return None; }
+        let h = random::mix(self.seed ^ ((x as u64) << 40) ^ ((y as u64) << 16) ^ face as u64);
+        // cada campo sale de su propia mezcla del hash, así no comparten bits
+        let field = |index: u64| random::mix(h ^ index);
+        let unit = |index: u64| (field(index) >> 40) as f32 / (1u64 << 24) as f32;
+        if unit(0) >= self.chance { return None; }
+        let size = 0.45 + 0.45 * unit(1);
+        Some(Decal {
+            texture: self.pool[(field(2) % self.pool.len() as u64) as usize],
+            u: unit(3) * (1.0 - size),
+            v: 0.15 + unit(4) * (0.85 - size).max(0.0),
+            size,
+            flip: field(5) & 1 == 1,
+        })
+    }
+
+    /// Pinta un tag del jugador centrado en `u` de la cara (a la altura de la vista)
+    pub fn spray(&mut self, cell: (usize, usize), face: Face, u: f32) {
+        let key = (cell.0, cell.1, face);
+        let decal = Decal {
+            texture: self.spray_texture,
+            u: (u - SPRAY_SIZE * 0.5).clamp(0.0, 1.0 - SPRAY_SIZE),
+            v: 0.5 - SPRAY_SIZE * 0.5,
+            size: SPRAY_SIZE,
+            flip: false,
+        };
+        self.sprays.entry(key).or_default().push(decal);
+        self.spray_order.push_back(key);
+        if self.spray_order.len() > MAX_SPRAYS
+            && let Some(oldest) = self.spray_order.pop_front()
+            && let Some(list) = self.sprays.get_mut(&oldest)
+        {
+            list.remove(0);
+        }
+    }
+}
+
+/// Tag de aerosol para cuando no hay `assets/tag.png`: mancha de borde irregular con chorreado
+fn generate_spray_tag(size: u32, color: Color) -> CpuTexture {
+    let mut pixels = vec![0u8; (size * size * 4) as usize];
+    let center = size as f32 * 0.5;
+    let radius = size as f32 * 0.3;
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x as f32 + 0.5 - center;
+            let dy = y as f32 + 0.5 - center;
+            let angle = dy.atan2(dx);
+            // borde ondulado y anillo interior más oscuro, como una firma rápida
+            let edge = radius * (1.0 + 0.18 * (angle * 5.0).sin() + 0.08 * (angle * 13.0 + 1.0).sin());
+            let d = dx.hypot(dy);
+            let ring = (d - radius * 0.55).abs() < size as f32 * 0.04;
+            // chorreado: franjas angostas que bajan desde la mitad inferior
+            let drip_column = ((x as f32 / size as f32) * 7.0 * PI).sin() > 0.92;
+            let drip = drip_column && dy > 0.0 && dy < radius * 1.5 + (x % 5) as f32 * 2.0;
+            if d > edge && !drip { continue; }
+            let shade = if ring { 0.55 } else { 1.0 - 0.25 * (d / edge).min(1.0) };
+            let i = ((y * size + x) * 4) as usize;
+            pixels[i] = (color.r as f32 * shade) as u8;
+            pixels[i + 1] = (color.g as f32 * shade) as u8;
+            pixels[i + 2] = (color.b as f32 * shade) as u8;
+            pixels[i + 3] = if d > edge { 200 } else { 235 };
+        }
+    }
+    CpuTexture::new(size, size, pixels)
+}
@@ -0,0 +1,106 @@
+use raylib::prelude::*;
+use crate::entities::PickupEffect;
+
+/// Color del objeto de tiempo extra (no deja efecto activo, pero comparte estilo con los demás)
+pub const TIME_BONUS_COLOR: Color = Color::new(90, 220, 255, 255);
+
+/// Efectos temporales que pueden estar activos a la vez
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EffectKind {
+    SpeedBoost,
+    MapReveal,
+    PoliceFreeze,
+}
+
+impl EffectKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EffectKind::SpeedBoost => "Speed",
+            EffectKind::MapReveal => "Map",
+            EffectKind::PoliceFreeze => "Freeze",
+        }
+    }
+
+    // Mismos colores que los orbes de cada objeto
+    pub fn color(self) -> Color {
+        match self {
+            EffectKind::SpeedBoost => Color::new(255, 150, 40, 255),
+            EffectKind::MapReveal => Color::new(120, 230, 90, 255),
+            EffectKind::PoliceFreeze => Color::new(150, 200, 255, 255),
+        }
+    }
+}
+
+struct ActiveEffect {
+    kind: EffectKind,
+    remaining: f32, // segundos que le quedan
+    duration: f32,  // duración total, para la barra del HUD
+    strength: f32,  // multiplicador (solo SpeedBoost)
+}
+
+/// Datos para dibujar un efecto activo en el HUD
+pub struct EffectIndicator {
+    pub label: &'static str,
+    pub remaining: f32,
+    pub fraction: f32, // [0,1] tiempo restante sobre el total
+    pub color: Color,
+}
+
+/// Efectos temporales activos del nivel y cuánto les queda
+pub struct ActiveEffects {
+    active: Vec<ActiveEffect>,
+}
+
+impl ActiveEffects {
+    pub fn new() -> Self {
+        Self { active: Vec::new() }
+    }
+
+    /// Activa el efecto temporal de un objeto recogido. Recoger otro igual renueva la duración.
+    /// Los efectos instantáneos (monedas y tiempo extra) los resuelve el juego con el evento.
+    pub fn apply(&mut self, effect: PickupEffect) {
+        let (kind, duration, strength) = match effect {
+            PickupEffect::SpeedBoost { multiplier, duration } => (EffectKind::SpeedBoost, duration, multiplier),
+            PickupEffect::MapReveal { duration } => (EffectKind::MapReveal, duration, 1.0),
+            PickupEffect::PoliceFreeze { duration } => (EffectKind::PoliceFreeze, duration, 1.0),
+            PickupEffect::Coin { .. } | PickupEffect::TimeBonus { .. } => return,
+        };
+
+        if let Some(current) = self.active.iter_mut().find(|e| e.kind == kind) {
+            current.remaining = current.remaining.max(duration);
+            current.duration = current.duration.max(duration);
+            current.strength = current.strength.max(strength);
+        } else {
+            self.active.push(ActiveEffect { kind, remaining: duration, duration, strength });
+        }
+    }
+
+    /// Descuenta el tiempo y quita los efectos vencidos
+    pub fn update(&mut self, dt: f32) {
+        for effect in self.active.iter_mut() {
+            effect.remaining -= dt;
+        }
+        self.active.retain(|e| e.remaining > 0.0);
+    }
+
+    pub fn is_active(&self, kind: EffectKind) -> bool {
+        self.active.iter().any(|e| e.kind == kind)
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        self.active.iter()
+            .find(|e| e.kind == EffectKind::SpeedBoost)
+            .map_or(1.0, |e| e.strength)
+    }
+
+    pub fn indicators(&self) -> Vec<EffectIndicator> {
+        self.active.iter()
+            .map(|e| EffectIndicator {
+                label: e.kind.label(),
+                remaining: e.remaining,
+                fraction: (e.remaining / e.duration.max(0.001)).clamp(0.0, 1.0),
+                color: e.kind.color(),
+            })
+            .collect()
+    }
+}
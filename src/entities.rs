@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use crate::random::rng;
+use raylib::prelude::*;
+use crate::maze::{Maze, is_walkable};
+use crate::player::Player;
+use crate::sprites::{AnimationState, Sprite};
+use crate::effects::{ActiveEffects, EffectKind, TIME_BONUS_COLOR};
+use crate::placement::Placement;
+use crate::audio::SpatialParams;
+
+/// Posición (píxeles del mundo) y orientación (radianes) de una entidad
+#[derive(Clone, Copy, Default)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+    pub facing: f32,
+}
+
+/// Se dibuja como sprite de frente a la cámara
+#[derive(Clone)]
+pub struct Billboard {
+    pub sprite_type: char,          // Hoja de sprites a usar (ver SpriteManager)
+    pub size: f32,                  // Tamaño en píxeles del mundo
+    pub animation: AnimationState,  // Animación actual
+    pub map_color: Option<Color>,   // Color en el minimapa; None = no aparece
+    pub map_hidden: bool,           // Solo aparece en el minimapa con el efecto MapReveal
+}
+
+/// Radio de contacto con el jugador
+#[derive(Clone, Copy)]
+pub struct Collider {
+    pub radius: f32,
+}
+
+/// Efecto que produce un objeto al recogerlo; los que tienen `duration` quedan activos ese tiempo
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PickupEffect {
+    Coin { bonus_seconds: f32 },                   // cuenta para abrir la salida y suma tiempo
+    TimeBonus { seconds: f32 },                    // tiempo extra
+    SpeedBoost { multiplier: f32, duration: f32 }, // el jugador corre más rápido
+    MapReveal { duration: f32 },                   // el minimapa muestra todo
+    PoliceFreeze { duration: f32 },                // la policía no se mueve ni atrapa
+}
+
+/// Se recoge al tocarlo (usa el radio del Collider)
+#[derive(Clone, Copy)]
+pub struct Pickup {
+    pub effect: PickupEffect,
+}
+
+/// Comportamiento automático de la entidad
+#[derive(Clone, Copy)]
+pub enum Ai {
+    /// Se queda en su lugar mirando al jugador
+    Guard,
+    /// Recorre los pasillos al azar, celda por celda
+    Patrol {
+        speed: f32, // píxeles por segundo
+        from: Option<(usize, usize)>,
+        target: Option<(usize, usize)>,
+    },
+}
+
+/// Eventos que una entidad dispara cuando el jugador entra en su radio
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TriggerEvent {
+    Exit,   // llegar a la salida
+    Siren,  // sonar la sirena de la policía
+    Caught, // la policía atrapa al jugador
+}
+
+#[derive(Clone, Copy)]
+pub struct Trigger {
+    pub radius: f32,
+    pub event: TriggerEvent,
+    pub cooldown: f32, // segundos mínimos entre disparos
+    pub timer: f32,
+}
+
+impl Trigger {
+    pub fn new(radius: f32, event: TriggerEvent, cooldown: f32) -> Self {
+        Self { radius, event, cooldown, timer: 0.0 }
+    }
+}
+
+/// Sonido en bucle que sale de la entidad (ej. la sirena de una patrulla)
+#[derive(Clone, Copy)]
+pub struct Emitter {
+    pub sound: &'static str, // nombre del sonido que carga el juego
+    pub params: SpatialParams,
+}
+
+/// Cuándo está activa una entidad según el estado de la salida
+#[derive(Clone, Copy, PartialEq)]
+pub enum ActiveWhen {
+    Always,
+    ExitLocked,   // mientras faltan monedas
+    ExitUnlocked, // cuando ya se juntaron todas
+}
+
+/// Entidad del nivel: un conjunto de componentes opcionales
+#[derive(Clone)]
+pub struct Entity {
+    pub kind: String,
+    pub transform: Transform,
+    pub active_when: ActiveWhen,
+    pub alive: bool,
+    pub billboard: Option<Billboard>,
+    pub collider: Option<Collider>,
+    pub pickup: Option<Pickup>,
+    pub ai: Option<Ai>,
+    pub triggers: Vec<Trigger>,
+    pub emitter: Option<Emitter>,
+}
+
+impl Entity {
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            transform: Transform::default(),
+            active_when: ActiveWhen::Always,
+            alive: true,
+            billboard: None,
+            collider: None,
+            pickup: None,
+            ai: None,
+            triggers: Vec::new(),
+            emitter: None,
+        }
+    }
+}
+
+/// Plantillas de entidades por nombre; algunas además se colocan con un caracter en el laberinto
+pub struct Prefabs {
+    templates: HashMap<String, Entity>,
+    markers: HashMap<char, String>,
+}
+
+impl Prefabs {
+    pub fn new() -> Self {
+        Self { templates: HashMap::new(), markers: HashMap::new() }
+    }
+
+    /// Registra una plantilla; `marker` es el caracter que la coloca desde el archivo del laberinto
+    pub fn register(&mut self, template: Entity, marker: Option<char>) {
+        if let Some(ch) = marker {
+            self.markers.insert(ch, template.kind.clone());
+        }
+        self.templates.insert(template.kind.clone(), template);
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&Entity> {
+        self.templates.get(kind)
+    }
+
+    /// Entidades del juego: moneda, policía de la salida, salida y patrulla
+    pub fn standard(block_size: usize) -> Self {
+        let bs = block_size as f32;
+        let mut prefabs = Self::new();
+
+        prefabs.register(Entity {
+            billboard: Some(Billboard {
+                sprite_type: 'c',
+                size: bs * 0.4, // moneda más chica (40% de la celda)
+                animation: AnimationState::new("spin"),
+                map_color: Some(Color::GOLD),
+                map_hidden: false,
+            }),
+            collider: Some(Collider { radius: 0.35 * bs }),
+            pickup: Some(Pickup { effect: PickupEffect::Coin { bonus_seconds: 5.0 } }),
+            ..Entity::new("coin")
+        }, Some('c'));
+
+        prefabs.register(Entity {
+            active_when: ActiveWhen::ExitLocked,
+            billboard: Some(Billboard {
+                sprite_type: 'P',
+                size: bs,
+                animation: AnimationState::new("flash"),
+                map_color: Some(Color::RED),
+                map_hidden: true,
+            }),
+            ai: Some(Ai::Guard),
+            triggers: vec![Trigger::new(1.1 * bs, TriggerEvent::Siren, 0.8)],
+            // la sirena en bucle sirve de guía hacia la puerta mientras está cerrada
+            emitter: Some(Emitter {
+                sound: "siren",
+                params: SpatialParams { volume: 0.35, max_distance: 8.0 * bs, ..SpatialParams::new(bs) },
+            }),
+            ..Entity::new("police")
+        }, None);
+
+        prefabs.register(Entity {
+            active_when: ActiveWhen::ExitUnlocked,
+            billboard: Some(Billboard {
+                sprite_type: 'Q',
+                size: bs,
+                animation: AnimationState::new("appear"),
+                map_color: Some(Color::GREEN),
+                map_hidden: true,
+            }),
+            triggers: vec![Trigger::new(0.7 * bs, TriggerEvent::Exit, 0.0)],
+            ..Entity::new("exit")
+        }, None);
+
+        prefabs.register(Entity {
+            billboard: Some(Billboard {
+                sprite_type: 'P',
+                size: bs * 0.8,
+                animation: AnimationState::new("flash"),
+                map_color: Some(Color::RED),
+                map_hidden: true,
+            }),
+            ai: Some(Ai::Patrol { speed: 1.5 * bs, from: None, target: None }),
+            triggers: vec![
+                Trigger::new(3.0 * bs, TriggerEvent::Siren, 2.0),
+                Trigger::new(0.4 * bs, TriggerEvent::Caught, 0.0),
+            ],
+            emitter: Some(Emitter {
+                sound: "siren",
+                params: SpatialParams { volume: 0.5, max_distance: 10.0 * bs, ..SpatialParams::new(bs) },
+            }),
+            ..Entity::new("patrol")
+        }, Some('P'));
+
+        // Objetos de poder: (nombre, marcador y hoja de sprites, color en el minimapa, efecto)
+        let power_ups = [
+            ("time_bonus", 't', TIME_BONUS_COLOR, PickupEffect::TimeBonus { seconds: 15.0 }),
+            ("speed_boost", 's', EffectKind::SpeedBoost.color(), PickupEffect::SpeedBoost { multiplier: 1.6, duration: 8.0 }),
+            ("map_reveal", 'm', EffectKind::MapReveal.color(), PickupEffect::MapReveal { duration: 10.0 }),
+            ("police_freeze", 'f', EffectKind::PoliceFreeze.color(), PickupEffect::PoliceFreeze { duration: 6.0 }),
+        ];
+        for (kind, marker, color, effect) in power_ups {
+            prefabs.register(Entity {
+                billboard: Some(Billboard {
+                    sprite_type: marker,
+                    size: bs * 0.35,
+                    animation: AnimationState::new("pulse"),
+                    map_color: Some(color),
+                    map_hidden: false,
+                }),
+                collider: Some(Collider { radius: 0.35 * bs }),
+                pickup: Some(Pickup { effect }),
+                ..Entity::new(kind)
+            }, Some(marker));
+        }
+
+        prefabs
+    }
+}
+
+/// Lo que pasó en el mundo durante un frame, para que el juego reaccione (sonidos, tiempo, estado).
+/// Cada evento lleva la posición de la entidad que lo produjo, para ubicar el sonido.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WorldEvent {
+    Picked(PickupEffect, (f32, f32)),
+    Triggered(TriggerEvent, (f32, f32)),
+}
+
+/// Todas las entidades del nivel actual y los efectos de objetos activos
+pub struct World {
+    pub entities: Vec<Entity>,
+    pub coins_total: usize,
+    pub coins_collected: usize,
+    pub effects: ActiveEffects,
+}
+
+impl World {
+    pub fn new(coins_total: usize) -> Self {
+        Self { entities: Vec::new(), coins_total, coins_collected: 0, effects: ActiveEffects::new() }
+    }
+
+    /// Crea una entidad a partir de su plantilla en la posición dada
+    pub fn spawn(&mut self, prefabs: &Prefabs, kind: &str, x: f32, y: f32) -> Option<&mut Entity> {
+        let mut entity = prefabs.get(kind)?.clone();
+        entity.transform.x = x;
+        entity.transform.y = y;
+        self.entities.push(entity);
+        self.entities.last_mut()
+    }
+
+    /// Crea entidades en las celdas con marcadores conocidos y deja esas celdas como pasillo
+    pub fn spawn_markers(&mut self, prefabs: &Prefabs, maze: &mut Maze, block_size: usize) {
+        for (j, row) in maze.iter_mut().enumerate() {
+            for (i, cell) in row.iter_mut().enumerate() {
+                if let Some(kind) = prefabs.markers.get(cell) {
+                    let (x, y) = cell_center(i, j, block_size);
+                    self.spawn(prefabs, kind, x, y);
+                    *cell = ' ';
+                }
+            }
+        }
+    }
+
+    /// Coloca `n` entidades del tipo dado en celdas elegidas según las reglas del nivel,
+    /// sin repetir celdas con otras entidades y separadas `min_spacing` de las del mismo tipo
+    pub fn spawn_random(&mut self, prefabs: &Prefabs, kind: &str, placement: &Placement, block_size: usize, n: usize, min_spacing: usize) {
+        let cell_of = |e: &Entity| (e.transform.x as usize / block_size, e.transform.y as usize / block_size);
+        let occupied: Vec<(usize, usize)> = self.entities.iter().map(cell_of).collect();
+        let same_kind: Vec<(usize, usize)> = self.entities.iter().filter(|e| e.kind == kind).map(cell_of).collect();
+
+        let mut random = rng();
+        for (i, j) in placement.choose_cells(n, min_spacing, &occupied, &same_kind) {
+            let (x, y) = cell_center(i, j, block_size);
+            if let Some(entity) = self.spawn(prefabs, kind, x, y) {
+                // desfase aleatorio para que las animaciones no vayan sincronizadas
+                if let Some(billboard) = entity.billboard.as_mut() {
+                    billboard.animation.elapsed = random.random_range(0.0..1.0);
+                }
+            }
+        }
+    }
+
+    /// Cantidad de entidades vivas de un tipo
+    pub fn count(&self, kind: &str) -> usize {
+        self.entities.iter().filter(|e| e.alive && e.kind == kind).count()
+    }
+
+    /// Índice (estable en `entities`), posición y si sigue viva, de cada entidad de un tipo
+    pub fn positions_of<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = (usize, f32, f32, bool)> + 'a {
+        self.entities.iter().enumerate()
+            .filter(move |(_, e)| e.kind == kind)
+            .map(|(i, e)| (i, e.transform.x, e.transform.y, e.alive))
+    }
+
+    pub fn exit_unlocked(&self) -> bool {
+        self.coins_collected >= self.coins_total
+    }
+
+    /// La policía (toda entidad con IA) está congelada por un objeto
+    fn is_frozen(&self, entity: &Entity) -> bool {
+        entity.ai.is_some() && self.effects.is_active(EffectKind::PoliceFreeze)
+    }
+
+    fn is_active(&self, entity: &Entity) -> bool {
+        entity.alive && match entity.active_when {
+            ActiveWhen::Always => true,
+            ActiveWhen::ExitLocked => !self.exit_unlocked(),
+            ActiveWhen::ExitUnlocked => self.exit_unlocked(),
+        }
+    }
+
+    /// Corre todos los sistemas del frame y devuelve los eventos producidos
+    pub fn update(&mut self, dt: f32, player: &Player, maze: &Maze, block_size: usize) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        self.effects.update(dt);
+        update_ai(self, dt, player, maze, block_size);
+        update_billboards(self, dt);
+        update_pickups(self, player, &mut events);
+        update_triggers(self, dt, player, &mut events);
+        events
+    }
+
+    /// Sprites de las entidades activas, listos para `render_sprites`
+    pub fn billboards(&self) -> Vec<Sprite> {
+        self.entities.iter()
+            .filter(|e| self.is_active(e))
+            .filter_map(|e| e.billboard.as_ref().map(|b| Sprite {
+                world_x: e.transform.x,
+                world_y: e.transform.y,
+                size: b.size,
+                sprite_type: b.sprite_type,
+                is_active: true,
+                animation: b.animation.clone(),
+                facing: e.transform.facing,
+            }))
+            .collect()
+    }
+
+    /// Posiciones de las entidades activas (y no congeladas) que emiten el sonido dado
+    pub fn emitters(&self, sound: &str) -> Vec<(f32, f32, SpatialParams)> {
+        self.entities.iter()
+            .filter(|e| self.is_active(e) && !self.is_frozen(e))
+            .filter_map(|e| e.emitter.filter(|em| em.sound == sound).map(|em| (e.transform.x, e.transform.y, em.params)))
+            .collect()
+    }
+
+    /// Posiciones de la policía activa y no congelada: toda entidad con IA, tanto la que
+    /// cuida la puerta como las patrullas
+    pub fn police_positions(&self) -> Vec<(f32, f32)> {
+        self.entities.iter()
+            .filter(|e| e.ai.is_some() && self.is_active(e) && !self.is_frozen(e))
+            .map(|e| (e.transform.x, e.transform.y))
+            .collect()
+    }
+
+    /// Posición y color de las entidades activas que se muestran en el minimapa;
+    /// las ocultas solo aparecen mientras dura el efecto MapReveal
+    pub fn map_icons(&self) -> Vec<(f32, f32, Color)> {
+        let revealed = self.effects.is_active(EffectKind::MapReveal);
+        self.entities.iter()
+            .filter(|e| self.is_active(e))
+            .filter_map(|e| {
+                let billboard = e.billboard.as_ref()?;
+                if billboard.map_hidden && !revealed { return None; }
+                Some((e.transform.x, e.transform.y, billboard.map_color?))
+            })
+            .collect()
+    }
+}
+
+fn cell_center(i: usize, j: usize, block_size: usize) -> (f32, f32) {
+    ((i * block_size + block_size / 2) as f32, (j * block_size + block_size / 2) as f32)
+}
+
+fn distance_to_player(entity: &Entity, player: &Player) -> f32 {
+    (entity.transform.x - player.position.x).hypot(entity.transform.y - player.position.y)
+}
+
+/// Sistema de IA: guardias que miran al jugador y patrullas que recorren el laberinto
+fn update_ai(world: &mut World, dt: f32, player: &Player, maze: &Maze, block_size: usize) {
+    if world.effects.is_active(EffectKind::PoliceFreeze) { return; }
+    let mut random = rng();
+    for entity in world.entities.iter_mut().filter(|e| e.alive) {
+        let transform = &mut entity.transform;
+        match entity.ai.as_mut() {
+            Some(Ai::Guard) => {
+                transform.facing = (player.position.y - transform.y).atan2(player.position.x - transform.x);
+            }
+            Some(Ai::Patrol { speed, from, target }) => {
+                let current = (transform.x as usize / block_size, transform.y as usize / block_size);
+                let (ti, tj) = match *target {
+                    Some(cell) => cell,
+                    None => {
+                        // Vecinos caminables; evita regresar salvo en un callejón sin salida
+                        let (ci, cj) = current;
+                        let mut options: Vec<(usize, usize)> = [(1i32, 0i32), (-1, 0), (0, 1), (0, -1)]
+                            .iter()
+                            .map(|(di, dj)| (ci as i32 + di, cj as i32 + dj))
+                            .filter(|&(ni, nj)| nj >= 0 && ni >= 0 && (nj as usize) < maze.len() && (ni as usize) < maze[nj as usize].len())
+                            .map(|(ni, nj)| (ni as usize, nj as usize))
+                            .filter(|&(ni, nj)| is_walkable(maze[nj][ni]))
+                            .collect();
+                        if options.len() > 1 {
+                            options.retain(|&cell| Some(cell) != *from);
+                        }
+                        let Some(&next) = options.choose(&mut random) else { continue };
+                        *from = Some(current);
+                        *target = Some(next);
+                        next
+                    }
+                };
+
+                let (tx, ty) = cell_center(ti, tj, block_size);
+                let dx = tx - transform.x;
+                let dy = ty - transform.y;
+                let distance = dx.hypot(dy);
+                let step = *speed * dt;
+                if distance <= step {
+                    transform.x = tx;
+                    transform.y = ty;
+                    *target = None;
+                } else {
+                    transform.x += dx / distance * step;
+                    transform.y += dy / distance * step;
+                    transform.facing = dy.atan2(dx);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Sistema de animación: avanza solo las entidades activas (y no congeladas), así una
+/// animación de aparición empieza desde cero cuando la entidad se activa
+fn update_billboards(world: &mut World, dt: f32) {
+    let active: Vec<bool> = world.entities.iter().map(|e| world.is_active(e) && !world.is_frozen(e)).collect();
+    for (entity, active) in world.entities.iter_mut().zip(active) {
+        if let (true, Some(billboard)) = (active, entity.billboard.as_mut()) {
+            billboard.animation.advance(dt);
+        }
+    }
+}
+
+/// Sistema de recolección: objetos con Pickup + Collider que el jugador toca
+fn update_pickups(world: &mut World, player: &Player, events: &mut Vec<WorldEvent>) {
+    let mut picked = Vec::new();
+    for (index, entity) in world.entities.iter().enumerate() {
+        if !world.is_active(entity) { continue; }
+        if let (Some(pickup), Some(collider)) = (entity.pickup, entity.collider)
+            && distance_to_player(entity, player) < collider.radius
+        {
+            picked.push((index, pickup.effect, (entity.transform.x, entity.transform.y)));
+        }
+    }
+
+    for (index, effect, position) in picked {
+        world.entities[index].alive = false;
+        if let PickupEffect::Coin { .. } = effect {
+            world.coins_collected += 1;
+        }
+        world.effects.apply(effect);
+        events.push(WorldEvent::Picked(effect, position));
+    }
+}
+
+/// Sistema de triggers: zonas que disparan eventos con el jugador dentro
+/// (la policía congelada no dispara los suyos)
+fn update_triggers(world: &mut World, dt: f32, player: &Player, events: &mut Vec<WorldEvent>) {
+    let active: Vec<bool> = world.entities.iter().map(|e| world.is_active(e) && !world.is_frozen(e)).collect();
+    for (entity, active) in world.entities.iter_mut().zip(active) {
+        let distance = distance_to_player(entity, player);
+        let position = (entity.transform.x, entity.transform.y);
+        for trigger in entity.triggers.iter_mut() {
+            trigger.timer = (trigger.timer - dt).max(0.0);
+            if active && trigger.timer <= 0.0 && distance < trigger.radius {
+                trigger.timer = trigger.cooldown;
+                events.push(WorldEvent::Triggered(trigger.event, position));
+            }
+        }
+    }
+}
@@ -1,32 +1,85 @@
+use std::time::{Duration, Instant};
 use raylib::prelude::*;
+use crate::capture;
+use crate::effects::EffectIndicator;
 
 pub struct Framebuffer {
     pub width: u32,
     pub height: u32,
-    pub color_buffer: Image,
+    pub pixels: Vec<u8>, // RGBA8, fila por fila
     background_color: Color,
     current_color: Color,
+    linear_filter: bool,           // filtro al escalar a la ventana (false = nearest)
+    texture: Option<Texture2D>,    // textura persistente donde se sube el frame
+    applied_filter: Option<bool>,  // filtro que ya tiene la textura
+    pub upload_time: Duration,     // lo que tardó la última subida a la GPU
+}
+
+/// Lo que el HUD dibuja este frame además de monedas y reloj
+pub struct Hud<'a> {
+    pub effects: &'a [EffectIndicator],
+    pub debug_lines: &'a [String], // panel de depuración (F3); vacío si está apagado
+    pub screenshot: Option<&'a str>, // ruta de la captura con HUD (Shift+F2), se toma antes del swap
+}
+
+/// Franja de columnas `[x0, x0 + width)` del frame: un pedazo de cada fila de `pixels`.
+/// Las franjas no se pisan, así que cada hilo de render pinta directo en la suya.
+pub struct Stripe<'a> {
+    pub x0: u32,
+    pub width: u32,
+    pub height: u32,
+    pub frame_width: u32, // ancho del frame completo (para proyectar rayos y sprites)
+    rows: Vec<&'a mut [u8]>,
+}
+
+impl Stripe<'_> {
+    /// Pinta un píxel; `x` va en coordenadas del frame y se ignora si cae fuera de la franja
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if x >= self.x0 && x < self.x0 + self.width && y < self.height {
+            let i = ((x - self.x0) * 4) as usize;
+            self.rows[y as usize][i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    /// Rellena las filas `[y0, y1)` de toda la franja con un color
+    pub fn fill_rows(&mut self, y0: u32, y1: u32, color: Color) {
+        let y0 = y0.min(self.height) as usize;
+        let y1 = y1.min(self.height) as usize;
+        for row in &mut self.rows[y0..y1] {
+            for pixel in row.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+    }
 }
 
 impl Framebuffer {
     pub fn new(width: u32, height: u32) -> Self {
-        let color_buffer = Image::gen_image_color(width as i32, height as i32, Color::BLACK);
         Framebuffer {
             width,
             height,
-            color_buffer,
+            pixels: vec![0; (width * height * 4) as usize],
             background_color: Color::BLACK,
             current_color: Color::WHITE,
+            linear_filter: false,
+            texture: None,
+            applied_filter: None,
+            upload_time: Duration::ZERO,
         }
     }
 
     pub fn clear(&mut self) {
-        self.color_buffer = Image::gen_image_color(self.width as i32, self.height as i32, self.background_color);
+        let c = self.background_color;
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[c.r, c.g, c.b, c.a]);
+        }
     }
 
     pub fn set_pixel(&mut self, x: u32, y: u32) {
         if x < self.width && y < self.height {
-            self.color_buffer.draw_pixel(x as i32, y as i32, self.current_color);
+            let i = ((y * self.width + x) * 4) as usize;
+            let c = self.current_color;
+            self.pixels[i..i + 4].copy_from_slice(&[c.r, c.g, c.b, c.a]);
         }
     }
 
@@ -38,6 +91,11 @@ impl Framebuffer {
         self.current_color = color;
     }
 
+    /// Filtro al escalar el frame a la ventana: lineal (suave) o nearest (pixelado)
+    pub fn set_linear_filter(&mut self, linear: bool) {
+        self.linear_filter = linear;
+    }
+
     pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
         let x0 = x.max(0) as u32;
         let y0 = y.max(0) as u32;
@@ -50,22 +108,87 @@ impl Framebuffer {
         }
     }
 
-    pub fn _render_to_file(&self, file_path: &str) {
-        let _ = self.color_buffer.export_image(file_path);
+    /// Divide el ancho en `count` franjas contiguas de columnas para pintarlas en paralelo
+    pub fn stripes(&mut self, count: usize) -> Vec<Stripe<'_>> {
+        let count = (count.max(1) as u32).min(self.width.max(1));
+        let base = self.width / count;
+        let extra = self.width % count;
+        let mut x0 = 0;
+        let mut stripes: Vec<Stripe> = (0..count)
+            .map(|i| {
+                let width = base + u32::from(i < extra);
+                let stripe = Stripe { x0, width, height: self.height, frame_width: self.width, rows: Vec::with_capacity(self.height as usize) };
+                x0 += width;
+                stripe
+            })
+            .collect();
+        // cada fila se corta en un pedazo por franja
+        for row in self.pixels.chunks_exact_mut((self.width * 4).max(1) as usize) {
+            let mut rest = row;
+            for stripe in &mut stripes {
+                let (piece, tail) = std::mem::take(&mut rest).split_at_mut((stripe.width * 4) as usize);
+                stripe.rows.push(piece);
+                rest = tail;
+            }
+        }
+        stripes
+    }
+
+    /// Guarda los píxeles como PNG (crea la carpeta si hace falta)
+    pub fn render_to_file(&self, file_path: &str) -> Result<(), String> {
+        if let Some(dir) = std::path::Path::new(file_path).parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("No se pudo crear {}: {}", dir.display(), e))?;
+        }
+        let image = image::RgbaImage::from_raw(self.width, self.height, self.pixels.clone())
+            .ok_or("Tamaño de framebuffer inválido")?;
+        image.save(file_path).map_err(|e| format!("No se pudo guardar {}: {}", file_path, e))
+    }
+
+    /// Sube los píxeles a la textura de la GPU; la textura se crea una sola vez por framebuffer
+    pub fn upload(&mut self, window: &mut RaylibHandle, raylib_thread: &RaylibThread) {
+        let start = Instant::now();
+        if self.texture.is_none() {
+            let blank = Image::gen_image_color(self.width as i32, self.height as i32, Color::BLACK);
+            self.texture = window.load_texture_from_image(raylib_thread, &blank).ok();
+            self.applied_filter = None;
+        }
+        if let Some(texture) = self.texture.as_mut() {
+            if self.applied_filter != Some(self.linear_filter) {
+                let filter = if self.linear_filter { TextureFilter::TEXTURE_FILTER_BILINEAR } else { TextureFilter::TEXTURE_FILTER_POINT };
+                texture.set_texture_filter(raylib_thread, filter);
+                self.applied_filter = Some(self.linear_filter);
+            }
+            if let Err(e) = texture.update_texture(&self.pixels) {
+                println!("[framebuffer] {}", e);
+            }
+        }
+        self.upload_time = start.elapsed();
+    }
+
+    /// Dibuja el último frame subido estirado a toda la ventana
+    pub fn draw_to_screen(&self, renderer: &mut RaylibDrawHandle) {
+        if let Some(texture) = self.texture.as_ref() {
+            let source = Rectangle::new(0.0, 0.0, self.width as f32, self.height as f32);
+            let dest = Rectangle::new(0.0, 0.0, renderer.get_screen_width() as f32, renderer.get_screen_height() as f32);
+            renderer.draw_texture_pro(texture, source, dest, Vector2::zero(), 0.0, Color::WHITE);
+        }
     }
 
     pub fn swap_buffers(
-        &self,
+        &mut self,
         window: &mut RaylibHandle,
         raylib_thread: &RaylibThread,
         coins_collected: usize,
         coins_total: usize,
         time_left_secs: u32,
+        hud: &Hud,
     ) {
-        if let Ok(texture) = window.load_texture_from_image(raylib_thread, &self.color_buffer) {
+        self.upload(window, raylib_thread);
+        {
             let mut renderer = window.begin_drawing(raylib_thread);
             renderer.clear_background(Color::BLACK);
-            renderer.draw_texture(&texture, 0, 0, Color::WHITE);
+            // El framebuffer puede ser más chico que la ventana (escala de resolución)
+            self.draw_to_screen(&mut renderer);
 
             // Tamanios para el HUD
             let screen_width = renderer.get_screen_width();
@@ -114,6 +237,53 @@ impl Framebuffer {
                 Color::RAYWHITE
             );
 
+            // Efectos activos: una caja por efecto debajo de los FPS, con barra de tiempo restante
+            let effect_font_size = 22;
+            let effect_box_width = 190;
+            let effect_box_height = 44;
+            let effect_box_x = screen_width - effect_box_width - 12;
+            for (index, effect) in hud.effects.iter().enumerate() {
+                let effect_box_y = fps_box_y + fps_box_height + 12 + index as i32 * (effect_box_height + 8);
+                renderer.draw_rectangle(
+                    effect_box_x + 3,
+                    effect_box_y + 3,
+                    effect_box_width,
+                    effect_box_height,
+                    Color::new(0, 0, 0, 80)
+                );
+                renderer.draw_rectangle(
+                    effect_box_x,
+                    effect_box_y,
+                    effect_box_width,
+                    effect_box_height,
+                    Color::new(10, 12, 20, 190)
+                );
+                renderer.draw_rectangle_lines(
+                    effect_box_x,
+                    effect_box_y,
+                    effect_box_width,
+                    effect_box_height,
+                    effect.color
+                );
+                let effect_label = format!("{} {:.0}s", effect.label, effect.remaining.ceil());
+                renderer.draw_text(
+                    &effect_label,
+                    effect_box_x + 12,
+                    effect_box_y + 6,
+                    effect_font_size,
+                    Color::RAYWHITE
+                );
+                // Barra de tiempo restante
+                let bar_width = ((effect_box_width - 24) as f32 * effect.fraction) as i32;
+                renderer.draw_rectangle(
+                    effect_box_x + 12,
+                    effect_box_y + effect_box_height - 10,
+                    bar_width,
+                    4,
+                    effect.color
+                );
+            }
+
             // Seteo para Timer
             let minutes_left = (time_left_secs / 60) as i32;
             let seconds_left = (time_left_secs % 60) as i32;
@@ -244,6 +414,40 @@ impl Framebuffer {
                 coin_font_size, 
                 Color::RAYWHITE
             );
+
+            // Panel de depuración: arriba del timer, una línea por etapa
+            if !hud.debug_lines.is_empty() {
+                let debug_font_size = 20;
+                let line_height = debug_font_size + 4;
+                let debug_box_width = hud.debug_lines.iter().map(|line| renderer.measure_text(line, debug_font_size)).max().unwrap_or(0) + 24;
+                let debug_box_height = hud.debug_lines.len() as i32 * line_height + 16;
+                let debug_box_x = 12;
+                let debug_box_y = time_box_y - debug_box_height - 12;
+                renderer.draw_rectangle(
+                    debug_box_x,
+                    debug_box_y,
+                    debug_box_width,
+                    debug_box_height,
+                    Color::new(10, 12, 20, 190)
+                );
+                for (index, line) in hud.debug_lines.iter().enumerate() {
+                    renderer.draw_text(
+                        line,
+                        debug_box_x + 12,
+                        debug_box_y + 8 + index as i32 * line_height,
+                        debug_font_size,
+                        Color::new(0, 218, 209, 255)
+                    );
+                }
+            }
+
+            // con el HUD completo y antes de mostrar el frame
+            if let Some(path) = hud.screenshot {
+                match capture::save_window_screenshot(&mut renderer, raylib_thread, path) {
+                    Ok(()) => println!("[screenshot] {}", path),
+                    Err(e) => println!("[screenshot] {}", e),
+                }
+            }
         }
     }
 }
\ No newline at end of file
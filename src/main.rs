@@ -4,17 +4,57 @@ mod caster;
 mod player;
 mod textures;
 mod sprites;
-
-use maze::{Maze, load_maze, find_char};
-use caster::cast_ray;
-use framebuffer::Framebuffer;
-use player::{Player, process_events};
+mod entities;
+mod effects;
+mod placement;
+mod audio;
+mod settings;
+mod menu;
+mod storage;
+mod score;
+mod cli;
+mod replay;
+mod random;
+mod manifest;
+mod decals;
+mod procedural;
+mod portals;
+mod postfx;
+mod capture;
+mod minimap;
+mod automap;
+mod debug;
+mod console;
+
+use maze::{Maze, Directives, load_maze, find_char};
+use caster::{Intersect, cast_ray, cast_view, column_angle, MIRROR_TINT};
+use framebuffer::{Framebuffer, Hud, Stripe};
+use player::{Player, InputFrame, process_events};
 use raylib::prelude::*;
-use crate::textures::TextureManager;
-use sprites::{SpriteManager as SpriteMgr, spawn_coins, render_sprites, pickup_coins, Sprite};
-use std::time::{Instant};
-
-enum GameState { Welcome, LevelPicker, Playing1, Playing2, Lost, Win }
+use crate::textures::{Sampling, TextureManager, TextureStore};
+use portals::Portals;
+use postfx::{FrameInfo, PostProcess};
+use capture::FrameCapture;
+use minimap::{Fog, render_minimap, render_minimap_rotating};
+use automap::Automap;
+use debug::{DebugMode, FrameTimings, render_debug_view};
+use console::{Console, Context};
+use sprites::{Sprite, SpriteManager as SpriteMgr, render_sprites};
+use entities::{World, Prefabs, WorldEvent, PickupEffect, TriggerEvent};
+use placement::{Placement, PlacementRules};
+use audio::{AudioManager, Listener, SpatialParams};
+use settings::Settings;
+use menu::{PauseMenu, PauseAction, WinScreen};
+use score::{Leaderboard, RunStats, Score};
+use cli::CliArgs;
+use replay::{Replay, ReplayFrame};
+use decals::SPRAY_REACH;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use rand::RngCore;
+use storage::SaveData;
+
+enum GameState { Welcome, LevelPicker, Playing1, Playing2, Paused, Lost, Win }
 
 struct Screens {
     welcome: Texture2D,
@@ -23,92 +63,18 @@ struct Screens {
     win: Texture2D,
 }
 
-// Colores para el minimap
-fn cell_to_color(cell: char) -> Color {
-    match cell {
-        '+' => Color::new(90, 140, 255, 255),   // azul eléctrico (más visible)
-        '-' => Color::new(0, 218, 209, 255),    // cian vibrante
-        '|' => Color::new(255, 120, 190, 255),  // rosa brillante
-        'g' => Color::new(255, 219, 88, 255),   // dorado cálido
-        _ => Color::LIGHTGRAY,
-    }
-}
-
-// Dibujar una celda en el minimap
-fn draw_cell_at(
-    framebuffer: &mut Framebuffer,
-    ox: i32,
-    oy: i32,
-    xo: usize,
-    yo: usize,
-    block_size: usize,
-    cell: char,
-) {
-    if cell == ' ' { return; }
-    let color = cell_to_color(cell);
-    framebuffer.set_current_color(color);
-    let x0 = ox + xo as i32;
-    let y0 = oy + yo as i32;
-    let pad = 1; // separación de 1px entre celdas, como un padding
-    framebuffer.fill_rect(
-        x0 + pad,
-        y0 + pad,
-        (block_size as i32 - 2 * pad).max(1),
-        (block_size as i32 - 2 * pad).max(1),
-    );
-}
-
-//Mapa 2D del laberinto
-fn render_minimap(framebuffer: &mut Framebuffer, maze: &Maze, block_size: usize, player: &Player, coins: &Vec<Sprite>, origin: (i32, i32), scale: f32) {
-    let mini = ((block_size as f32) * scale) as usize;
-    let (ox, oy) = origin;
-
-    let mw = (maze[0].len() * mini) as i32;
-    let mh = (maze.len() * mini) as i32;
-    // fondo del minimapa (teal más claro)
-    framebuffer.set_current_color(Color::new(60, 190, 180, 230));
-    framebuffer.fill_rect(ox, oy, mw, mh);
-    // borde fino (navy)
-    framebuffer.set_current_color(Color::new(24, 32, 56, 255));
-    framebuffer.fill_rect(ox - 1, oy - 1, mw + 2, 1);
-    framebuffer.fill_rect(ox - 1, oy + mh, mw + 2, 1);
-    framebuffer.fill_rect(ox - 1, oy - 1, 1, mh + 2);
-    framebuffer.fill_rect(ox + mw, oy - 1, 1, mh + 2);
-
-    for (row_index, row) in maze.iter().enumerate() {
-        for (col_index, &cell) in row.iter().enumerate() {
-            let xo = col_index * mini;
-            let yo = row_index * mini;
-            draw_cell_at(framebuffer, ox, oy, xo, yo, mini, cell);
-        }
-    }
-
-    // monedas en el minimapa (vivas)
-    for s in coins.iter().filter(|s| s.is_active && s.sprite_type == 'c') {
-        let cx = (s.world_x / block_size as f32) * mini as f32;
-        let cy = (s.world_y / block_size as f32) * mini as f32;
-        framebuffer.set_current_color(Color::GOLD);
-        framebuffer.fill_rect(ox + cx as i32 - 2, oy + cy as i32 - 2, 4, 4);
-    }
-
-    // jugador en el minimapa (posición subcelda para movimiento suave)
-    let pmini_x = (player.position.x / block_size as f32) * mini as f32;
-    let pmini_y = (player.position.y / block_size as f32) * mini as f32;
-    framebuffer.set_current_color(Color::BLACK);
-    framebuffer.fill_rect(ox + pmini_x as i32 - 2, oy + pmini_y as i32 - 2, 5, 5);
-
-}
-
+/// Pinta cielo, piso y paredes de una franja de columnas; devuelve la profundidad
+/// de cada columna de la franja (índice 0 = columna `stripe.x0`) y el impacto de su rayo
+/// (para la niebla del minimapa y la vista cenital)
 fn render_world(
-    framebuffer: &mut Framebuffer,
-    maze: &Maze,
-    block_size: usize,
+    stripe: &mut Stripe,
+    level: (&Maze, &Portals, usize),
     player: &Player,
-    texman: &mut TextureManager,
-) -> Vec<f32> {
-    let num_rays = framebuffer.width; // 1 rayo x col de framebuffer
-    let hh = framebuffer.height as f32 / 2.0; // half height
-    let mut zbuf = vec![f32::INFINITY; framebuffer.width as usize];
+    texman: &TextureManager,
+) -> (Vec<f32>, Vec<Intersect>) {
+    let num_rays = stripe.frame_width; // 1 rayo x col de framebuffer
+    let hh = stripe.height as f32 / 2.0; // half height
+    let mut zbuf = vec![f32::INFINITY; stripe.width as usize];
 
     // Cielo en degradado (celeste claro -> blanco azulado)
     let sky_top = Color::new(179, 229, 252, 255);   // celeste claro
@@ -119,54 +85,106 @@ fn render_world(
         let r = (sky_top.r as f32 * (1.0 - t) + sky_bot.r as f32 * t) as u8;
         let g = (sky_top.g as f32 * (1.0 - t) + sky_bot.g as f32 * t) as u8;
         let b = (sky_top.b as f32 * (1.0 - t) + sky_bot.b as f32 * t) as u8;
-        framebuffer.set_current_color(Color::new(r, g, b, 255));
-        framebuffer.fill_rect(0, y, framebuffer.width as i32, 1);
+        stripe.fill_rows(y as u32, y as u32 + 1, Color::new(r, g, b, 255));
     }
 
     // Piso color teal oscuro
-    framebuffer.set_current_color(Color::new(26, 120, 112, 255));
-    framebuffer.fill_rect(0, hh as i32, framebuffer.width as i32, hh as i32);
+    stripe.fill_rows(hh as u32, stripe.height, Color::new(26, 120, 112, 255));
 
     // Distancia del plano de proyección
-    let dist_plane = (framebuffer.width as f32 / 2.0) / (player.field_of_view / 2.0).tan();
-    let bs = block_size as f32;
+    let dist_plane = (stripe.frame_width as f32 / 2.0) / (player.field_of_view / 2.0).tan();
+    let bs = level.2 as f32;
+    let mut column_decals = Vec::new();
+    let sampling = texman.store.sampling();
 
-    for i in 0..num_rays {
-        let current_ray = i as f32 / num_rays as f32; // [0,1)
-        let a = player.angle - (player.field_of_view / 2.0) + (player.field_of_view * current_ray);
-        let hit = cast_ray(framebuffer, maze, player, a, block_size, false);
+    let hits = cast_view(level, player, stripe.x0..stripe.x0 + stripe.width, num_rays);
+    for (i, hit) in (stripe.x0..).zip(&hits) {
+        let a = column_angle(player, i, num_rays);
 
         // Corrección de "fisheye"
-        let mut perp = hit.hit_distance * (a - player.angle).cos().abs();
+        let fisheye = (a - player.angle).cos().abs();
+        let mut perp = hit.hit_distance * fisheye;
         let near = 0.35 * bs; // 35% del tamaño de la celda
         if perp < near { perp = near; }
-        zbuf[i as usize] = perp;
+        // los sprites se tapan con el espejo o portal, no con la pared que se ve a través
+        zbuf[(i - stripe.x0) as usize] = (hit.surface_distance * fisheye).max(near);
 
         // Altura de la columna de la estaca
-        let stake_height = ((bs * dist_plane) / perp).min(framebuffer.height as f32 * 0.9);
+        let stake_height = ((bs * dist_plane) / perp).min(stripe.height as f32 * 0.9);
         let stake_top = (hh - stake_height * 0.5) as i32;
         let stake_bottom = (hh + stake_height * 0.5) as i32;
 
-        // Dimensiones de la textura para el tipo de pared impactada
-        let (tw_u, th_u) = texman.get_image_size(hit.wall_type);
-        let tw = tw_u as i32; let th = th_u as i32;
+        // Textura (y sus dimensiones) para el tipo de pared impactada
+        // (animada: el frame y el desplazamiento dependen del tiempo de juego)
+        let (texture, (scroll_u, scroll_v)) = texman.wall(hit);
 
-        // Coordenada X dentro de la textura usando fracción robusta provista por el raycast
-        let tex_x = (hit.texture_coord * tw as f32).clamp(0.0, tw as f32 - 1.0) as i32;
+        // Coordenada U dentro de la textura usando fracción robusta provista por el raycast
+        let tex_u = hit.texture_coord + scroll_u;
+
+        // Nivel de mipmap: cuántos texels de alto caen en cada píxel de la columna
+        let lod = (texture.height as f32 / stake_height.max(1.0)).log2();
+
+        // Graffiti de esta cara que cruzan la columna
+        column_decals.clear();
+        if let Some(cell) = hit.cell {
+            column_decals.extend(texman.decals.on_face(cell, hit.face).filter(|d| d.covers_column(hit.texture_coord)));
+        }
 
         // Pintar la columna muestreando la textura y sombreando por distancia
         let y_start = stake_top.max(0);
-        let y_end = stake_bottom.min(framebuffer.height as i32 - 1);
+        let y_end = stake_bottom.min(stripe.height as i32 - 1);
         for y in y_start..=y_end {
             let v = (y as f32 - y_start as f32) / ((y_end - y_start).max(1) as f32);
-            let tex_y = (v * th as f32).clamp(0.0, th as f32 - 1.0) as i32;
 
-            let c = texman.get_pixel_color_mut(hit.wall_type, tex_x as u32, tex_y as u32);
-            framebuffer.set_current_color(c);
-            framebuffer.set_pixel(i, y as u32);
+            let mut c = texture.sample(tex_u, v.min(0.9999) + scroll_v, lod, sampling);
+            for decal in &column_decals {
+                c = decal.paint(&texman.store, (hit.texture_coord, v), stake_height, c);
+            }
+            for _ in 0..hit.reflections {
+                let f = |v: u8, t: u8| (v as u16 * t as u16 / 255) as u8;
+                c = Color::new(f(c.r, MIRROR_TINT.r), f(c.g, MIRROR_TINT.g), f(c.b, MIRROR_TINT.b), 255);
+            }
+            stripe.set_pixel(i, y as u32, c);
         }
     }
-    zbuf
+    (zbuf, hits)
+}
+
+/// Renderiza la vista 3D (paredes y sprites) repartiendo franjas de columnas entre `threads`
+/// hilos. Cada franja respeta su propio z-buffer, así que los sprites se recortan igual que
+/// con un solo hilo. Devuelve el impacto del rayo de cada columna.
+fn render_view(
+    framebuffer: &mut Framebuffer,
+    level: (&Maze, &Portals, usize),
+    player: &Player,
+    texman: &TextureManager,
+    (sprites, spriteman): (&[Sprite], &SpriteMgr),
+    threads: usize,
+    timings: &mut FrameTimings,
+) -> Vec<Intersect> {
+    let block_size = level.2;
+    let render_stripe = |stripe: &mut Stripe| {
+        let start = Instant::now();
+        let (zbuf, hits) = render_world(stripe, level, player, texman);
+        let rays = start.elapsed();
+        render_sprites(stripe, player, sprites, spriteman, &texman.store, block_size, &zbuf);
+        (hits, rays, start.elapsed() - rays)
+    };
+
+    let mut stripes = framebuffer.stripes(threads);
+    let results = if stripes.len() == 1 {
+        vec![render_stripe(&mut stripes[0])]
+    } else {
+        let render_stripe = &render_stripe;
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = stripes.iter_mut().map(|stripe| scope.spawn(move || render_stripe(stripe))).collect();
+            handles.into_iter().map(|handle| handle.join().expect("falló un hilo de render")).collect::<Vec<_>>()
+        })
+    };
+    // cuenta la franja más lenta; las franjas van de izquierda a derecha, así que se concatenan en orden
+    timings.rays = results.iter().map(|r| r.1).max().unwrap_or_default();
+    timings.sprites = results.iter().map(|r| r.2).max().unwrap_or_default();
+    results.into_iter().flat_map(|r| r.0).collect()
 }
 
 fn draw_fullscreen_screen(
@@ -174,10 +192,23 @@ fn draw_fullscreen_screen(
     thread: &RaylibThread,
     tex: &Texture2D,
     fade_t: f32, // 0=no fade overlay, 1=negro total
+    lines: &[String], // texto extra abajo a la izquierda (ej. mejores tiempos)
 ) {
     let mut d = window.begin_drawing(thread);
     d.clear_background(Color::BLACK);
-    d.draw_texture(tex, 0, 0, Color::WHITE);
+    // la imagen se estira al tamaño de la ventana (puede venir de las opciones)
+    let source = Rectangle::new(0.0, 0.0, tex.width as f32, tex.height as f32);
+    let dest = Rectangle::new(0.0, 0.0, d.get_screen_width() as f32, d.get_screen_height() as f32);
+    d.draw_texture_pro(tex, source, dest, Vector2::zero(), 0.0, Color::WHITE);
+
+    let font_size = 26;
+    let line_y0 = d.get_screen_height() - 24 - lines.len() as i32 * (font_size + 8);
+    for (index, line) in lines.iter().enumerate() {
+        let y = line_y0 + index as i32 * (font_size + 8);
+        let w = d.measure_text(line, font_size);
+        d.draw_rectangle(16, y - 4, w + 20, font_size + 8, Color::new(10, 12, 20, 190));
+        d.draw_text(line, 26, y, font_size, Color::RAYWHITE);
+    }
 
     if fade_t > 0.0 {
         let a = (fade_t.clamp(0.0, 1.0) * 255.0) as u8;
@@ -185,36 +216,174 @@ fn draw_fullscreen_screen(
     }
 }
 
+// Carga un nivel y sus entidades; devuelve las directivas para lo que se aplica afuera (ej. @palette)
 fn start_level(
-    which: usize,
+    maze_file: &str,
+    seed: Option<u64>,
     block_size: usize,
     player: &mut Player,
     maze_out: &mut Maze,
-    coins_out: &mut Vec<Sprite>,
-    keys_total_out: &mut usize,
-) -> ((f32,f32), Instant)
+    world_out: &mut World,
+    prefabs: &Prefabs,
+) -> Directives
 {
-    let maze_file = if which == 1 { "maze1.txt" } else { "maze2.txt" };
-    *maze_out = load_maze(maze_file);
-    *keys_total_out = if which == 1 { 5 } else { 8 };
+    // con semilla fija el nivel (y la IA) sale igual cada vez
+    if let Some(seed) = seed { random::seed(seed); }
+    let (maze, directives) = load_maze(maze_file);
+    *maze_out = maze;
+
+    // directivas del nivel: monedas necesarias (@coins N) y objetos al azar (@spawn tipo N)
+    let mut coins_total = 5;
+    let mut random_pickups: Vec<(String, usize)> = Vec::new();
+    for (key, value) in &directives {
+        match key.as_str() {
+            "coins" => coins_total = value.parse().unwrap_or(coins_total),
+            "spawn" => {
+                if let Some((kind, count)) = value.split_once(' ') {
+                    random_pickups.push((kind.to_string(), count.trim().parse().unwrap_or(1)));
+                }
+            }
+            _ => {}
+        }
+    }
 
     // posicion jugador en 'p'
-    let (si, sj) = find_char(&maze_out, 'p').unwrap_or((1, 1));
+    let (si, sj) = find_char(maze_out, 'p').unwrap_or((1, 1));
     let sx = (si * block_size + block_size / 2) as f32;
     let sy = (sj * block_size + block_size / 2) as f32;
     player.position = Vector2::new(sx, sy);
 
-    // monedas
-    *coins_out = spawn_coins(&maze_out, block_size, *keys_total_out);
+    // entidades marcadas en el laberinto (monedas fijas, objetos, patrullas)
+    *world_out = World::new(coins_total);
+    world_out.spawn_markers(prefabs, maze_out, block_size);
 
-    // salida, policia o llave dependiendo de momento
-    let (gi, gj) = find_char(&maze_out, 'g').unwrap_or((maze_out[0].len()-2, maze_out.len()-2));
-    let gate_pos = (
-        (gi * block_size + block_size/2) as f32,
-        (gj * block_size + block_size/2) as f32
-    );
+    // el resto de monedas y los objetos al azar, según las reglas de colocación
+    let placement = Placement::new(maze_out, PlacementRules::from_directives(&directives), (si, sj));
+    let random_coins = coins_total.saturating_sub(world_out.count("coin"));
+    world_out.spawn_random(prefabs, "coin", &placement, block_size, random_coins, placement.rules.min_coin_spacing);
+    for (kind, count) in &random_pickups {
+        world_out.spawn_random(prefabs, kind, &placement, block_size, *count, placement.rules.min_item_spacing);
+    }
+
+    // salida: policía custodiando hasta juntar las monedas, luego la llave
+    let (gi, gj) = find_char(maze_out, 'g').unwrap_or((maze_out[0].len()-2, maze_out.len()-2));
+    let gx = (gi * block_size + block_size/2) as f32;
+    let gy = (gj * block_size + block_size/2) as f32;
+    let facing = (sy - gy).atan2(sx - gx); // mira hacia la entrada
+    for kind in ["police", "exit"] {
+        if let Some(entity) = world_out.spawn(prefabs, kind, gx, gy) {
+            entity.transform.facing = facing;
+        }
+    }
+    directives
+}
+
+/// Reloj, estadísticas y banderas de la partida en curso; se rehacen al empezar un nivel
+struct LevelRun {
+    total: Duration,   // tiempo total del nivel (con bonus)
+    elapsed: Duration, // solo avanza mientras se juega (no en pausa)
+    lost: bool,
+    stats: RunStats,
+    fresh: bool,       // primer frame del nivel (para empezar la grabación)
+}
 
-    (gate_pos, Instant::now())
+impl LevelRun {
+    fn new() -> Self {
+        Self { total: Duration::from_secs(60), elapsed: Duration::ZERO, lost: false, stats: RunStats::default(), fresh: false }
+    }
+}
+
+// Empieza (o reinicia) un nivel: carga el laberinto y sus entidades, la paleta, los portales,
+// la niebla y el mapa en limpio, y una partida nueva. Todos los caminos al juego pasan por acá.
+fn begin_level(
+    file: &str,
+    (seed, block_size): (Option<u64>, usize),
+    player: &mut Player,
+    (maze, world, portals): (&mut Maze, &mut World, &mut Portals),
+    (texman, fog, automap): (&mut TextureManager, &mut Fog, &mut Automap),
+    run: &mut LevelRun,
+    prefabs: &Prefabs,
+) {
+    let directives = start_level(file, seed, block_size, player, maze, world, prefabs);
+    texman.load_level(&directives, random::rng().next_u64());
+    *portals = Portals::from_directives(maze, &directives);
+    *fog = Fog::new(maze);
+    automap.reset();
+    *run = LevelRun { fresh: true, ..LevelRun::new() };
+}
+
+// Estado de cada nivel para la pantalla de selección: mejor tiempo y su top 3
+fn picker_lines(save: &SaveData, leaderboard: &Leaderboard) -> Vec<String> {
+    (1..=2)
+        .map(|level| {
+            let mut line = if let Some(best) = save.best_times.get(&level) {
+                format!("Nivel {}: mejor tiempo {:.1}s", level, best)
+            } else {
+                format!("Nivel {}: sin completar", level)
+            };
+            // los 3 mejores puntajes del nivel
+            let top: Vec<String> = leaderboard.entries(level).iter()
+                .take(3)
+                .map(|e| format!("{} {}", e.initials, e.score))
+                .collect();
+            if !top.is_empty() {
+                line.push_str(&format!("  |  {}", top.join(", ")));
+            }
+            line
+        })
+        .collect()
+}
+
+// Aplica las opciones del menú al jugador, al audio y a la resolución interna
+// (se llama también cuando cambia el tamaño de la ventana)
+fn apply_settings(
+    settings: &Settings,
+    player: &mut Player,
+    audio: &mut AudioManager,
+    framebuffer: &mut Framebuffer,
+    textures: &mut TextureStore,
+    window_size: (i32, i32),
+) {
+    // El FOV de las opciones es el horizontal con la proporción original (1300x900);
+    // con otra proporción se mantiene el vertical y se ve más (o menos) a los lados
+    const REFERENCE_ASPECT: f32 = 1300.0 / 900.0;
+    let aspect = window_size.0 as f32 / window_size.1.max(1) as f32;
+    let half_fov = (settings.fov().to_radians() / 2.0).tan() * aspect / REFERENCE_ASPECT;
+    player.field_of_view = 2.0 * half_fov.atan();
+    player.mouse_sensitivity = settings.mouse_sensitivity;
+    audio.master_volume = settings.master_volume;
+    audio.music_volume = settings.music_volume;
+    audio.sfx_volume = settings.sfx_volume;
+
+    let width = ((window_size.0 as f32 * settings.resolution_scale) as u32).max(1);
+    let height = ((window_size.1 as f32 * settings.resolution_scale) as u32).max(1);
+    if width != framebuffer.width || height != framebuffer.height {
+        let mut resized = Framebuffer::new(width, height);
+        resized.set_background_color(Color::new(50, 50, 100, 255));
+        *framebuffer = resized;
+    }
+    framebuffer.set_linear_filter(settings.linear_filter);
+    textures.set_sampling(Sampling { mipmaps: settings.mipmaps, bilinear: settings.bilinear_textures });
+}
+
+// Archivo de laberinto de cada nivel del juego
+fn level_file(level: usize) -> String {
+    format!("maze{}.txt", level)
+}
+
+// Nivel pedido por nombre (1, level1, maze1...) o ruta a un archivo; los archivos
+// que no son niveles del juego se juegan como nivel 0 (sin récords)
+fn resolve_level(name: &str) -> Option<(usize, String)> {
+    let digits = name.trim_start_matches("level").trim_start_matches("maze").trim_end_matches(".txt");
+    if let Ok(level @ 1..=2) = digits.parse::<usize>() {
+        return Some((level, level_file(level)));
+    }
+    Path::new(name).is_file().then(|| (0, name.to_string()))
+}
+
+// Estado de juego según el nivel (cambia la música)
+fn playing_state(level: usize) -> GameState {
+    if level == 2 { GameState::Playing2 } else { GameState::Playing1 }
 }
 
 // Que deje continuar con space o enter
@@ -238,19 +407,82 @@ fn pressed_two(win: &RaylibHandle) -> bool {
 pub fn main() {
     use raylib::prelude::*;
     use std::f32::consts::PI;
-    use std::time::Duration;
 
-    let window_width = 1300;
-    let window_height = 900;
+    // Argumentos de la línea de comandos (ver cli::USAGE)
+    let cli = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    // Repetición: trae su propio nivel y semilla
+    let mut replay = match cli.replay.as_deref().map(Replay::load) {
+        Some(Ok(replay)) => {
+            println!("[replay] {} | seed={} | {} frames", replay.level, replay.seed, replay.frame_count());
+            Some(replay)
+        }
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    // al grabar sin --seed se elige una, para poder repetir la partida
+    let fixed_seed = replay.as_ref().map(|r| r.seed)
+        .or(cli.seed)
+        .or_else(|| cli.record.as_ref().map(|_| rand::random()));
+    let mut recorder = cli.record.as_ref().map(|_| Replay::new("", fixed_seed.unwrap_or(0)));
+
+    // --capture: secuencia de frames con paso fijo (para videos)
+    let mut capture = match cli.capture.as_deref().map(|dir| FrameCapture::new(dir, cli.capture_every.unwrap_or(1), cli.capture_fps.unwrap_or(60.0))) {
+        Some(Ok(capture)) => Some(capture),
+        Some(Err(e)) => {
+            println!("[capture] {}", e);
+            None
+        }
+        None => None,
+    };
+
+    let start_at = match replay.as_ref().map(|r| r.level.clone()).or(cli.level.clone()) {
+        Some(name) => match resolve_level(&name) {
+            Some(level) => Some(level),
+            None => {
+                eprintln!("No se encontró el nivel: {}", name);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    // Opciones y progreso guardados en el directorio de configuración
+    let mut settings = Settings::load();
+    let mut save = SaveData::load();
+    let mut leaderboard = Leaderboard::load();
+    settings.fov_override = cli.fov;
+
+    let (window_width, window_height) = cli.resolution.unwrap_or((settings.window_width, settings.window_height));
     let block_size = 100;
 
-    let (mut window, raylib_thread) = raylib::init()
+    let mut builder = raylib::init();
+    builder
         .size(window_width, window_height)
         .title("Subway Surfer")
         .log_level(TraceLogLevel::LOG_WARNING)
-        .build();
+        .resizable();
+    if cli.fullscreen { builder.fullscreen(); }
+    let (mut window, raylib_thread) = builder.build();
 
     window.disable_cursor();
+    // Esc pausa el juego en vez de cerrar la ventana
+    window.set_exit_key(None);
+
+    let mut pause_menu = PauseMenu::new();
 
     let screens = Screens {
         welcome: window.load_texture(&raylib_thread, "assets/welcome_screen.png").expect("welcome_screen"),
@@ -259,22 +491,45 @@ pub fn main() {
         win: window.load_texture(&raylib_thread, "assets/win.png").expect("win"),
     };
 
-    let mut framebuffer = Framebuffer::new(window_width as u32, window_height as u32);
+    // Tamaño real de la ventana (cambia al redimensionar o con pantalla completa)
+    let mut window_size = (window.get_screen_width(), window.get_screen_height());
+    let mut borderless = false; // F11; raylib no informa este estado
+    let mut hud_shot: Option<String> = None; // captura con HUD pedida (Shift+F2), para el próximo HUD
+    let mut exit_after_shot = false;         // la captura pendiente es la de --screenshot-at
+    let mut framebuffer = Framebuffer::new(
+        (window_size.0 as f32 * settings.resolution_scale) as u32,
+        (window_size.1 as f32 * settings.resolution_scale) as u32,
+    );
     framebuffer.set_background_color(Color::new(50, 50, 100, 255));
 
-    let audio = RaylibAudio::init_audio_device().expect("No se pudo iniciar el audio");
-
-    // Música de fondo
-    let theme = audio.new_music("assets/theme.mp3").expect("No se pudo cargar assets/theme.mp3");
-    theme.set_volume(0.6);
-    theme.play_stream();
-
-    // SFX
-    let police_snd = audio.new_sound("assets/police.mp3").expect("No se pudo cargar assets/police.mp3");
-    let coin_snd   = audio.new_sound("assets/coin.mp3").expect("No se pudo cargar assets/coin.mp3");
-    let key_snd    = audio.new_sound("assets/key.mp3").expect("No se pudo cargar assets/key.mp3");
+    // Audio: si no hay dispositivo el juego sigue en silencio
+    let audio_device = if cli.no_audio {
+        None
+    } else {
+        RaylibAudio::init_audio_device()
+            .map_err(|e| println!("[audio] no se pudo iniciar el audio: {}", e))
+            .ok()
+    };
+    let mut audio = AudioManager::new(audio_device.as_ref());
+
+    // Música por pantalla y nivel (si falta la pista propia se usa el tema principal)
+    audio.load_music("menu", &["assets/theme.mp3"]);
+    audio.load_music("level1", &["assets/level1.mp3", "assets/theme.mp3"]);
+    audio.load_music("level2", &["assets/level2.mp3", "assets/theme.mp3"]);
+    audio.load_music("lost", &["assets/lost.mp3", "assets/theme.mp3"]);
+    audio.load_music("win", &["assets/win.mp3", "assets/theme.mp3"]);
+
+    // SFX; la sirena en bucle de las patrullas es otra instancia para no cortar la de los triggers
+    audio.load_sound("police", "assets/police.mp3");
+    audio.load_sound("coin", "assets/coin.mp3");
+    audio.load_sound("key", "assets/key.mp3");
+    audio.load_sound("siren", "assets/police.mp3");
+    let sfx_params = SpatialParams::new(block_size as f32);
 
     let mut maze: Maze = Vec::new();
+    let mut portals = Portals::new();
+    let mut fog = Fog::new(&maze);
+    let mut automap = Automap::new();
 
     let mut state = GameState::Welcome;
 
@@ -285,11 +540,8 @@ pub fn main() {
     let mut fade_dir: f32 = 0.0;        
     let mut next_state: Option<GameState> = None;
 
-    // nivel y llaves por nivel
-    let mut coins_total: usize = 5;
-
     // Cargar texturas de paredes (wall + graffiti)
-    let mut texman = TextureManager::new(&mut window, &raylib_thread).expect("Error cargando texturas");
+    let mut texman = TextureManager::new().expect("Error cargando texturas");
 
     let mut player = Player::new(
         Vector2::new(100.0, 100.0), 
@@ -297,28 +549,108 @@ pub fn main() {
         std::f32::consts::PI / 3.0,
     );
 
-    // Cargar sprites
-    let mut spriteman = SpriteMgr::new().expect("Error cargando sprites");
-
-    // Lista de monedas (se crea al seleccionar nivel) y posición de portal
-    let mut coins: Vec<Sprite> = Vec::new();
-    let mut gate_pos: (f32, f32) = (0.0, 0.0);
+    apply_settings(&settings, &mut player, &mut audio, &mut framebuffer, &mut texman.store, window_size);
+    let mut postfx = PostProcess::from_settings(&settings);
+    let mut debug_mode = DebugMode::Off;
+    let mut console = Console::new();
+    textures::register_console_commands(&mut console);
+    let mut timings = FrameTimings::default();
 
-    // Contador
-    let mut _collected = 0usize;
+    // Cargar sprites
+    let spriteman = SpriteMgr::new(&mut texman.store).expect("Error cargando sprites");
+
+    // Plantillas de entidades y entidades del nivel (se crean al seleccionar nivel)
+    let prefabs = Prefabs::standard(block_size);
+    let mut world = World::new(0);
+
+    // Timer de nivel
+    let mut run = LevelRun::new();
+    let mut current_level = 1;
+    let mut current_file = level_file(1);
+    let mut win_screen: Option<WinScreen> = None;
+
+    // --level / --replay: directo al nivel, sin menús
+    if let Some((level, file)) = start_at {
+        begin_level(&file, (fixed_seed, block_size), &mut player, (&mut maze, &mut world, &mut portals), (&mut texman, &mut fog, &mut automap), &mut run, &prefabs);
+        current_level = level; current_file = file;
+        println!("[state] Start {} | coins_total={} | entities={}", current_file, world.coins_total, world.entities.len());
+        state = playing_state(level);
+    }
 
-    // Timer de nivel y estado de audio/proximidad
-    let mut level_total = Duration::from_secs(60);
-    let mut level_start = Instant::now();
-    let mut lost = false;
-    let mut last_police = Instant::now() - Duration::from_millis(1000);
-    let mut prev_collected = 0usize;
-    let mut _picked_key = false;
+    // Segundos desde que abrió la ventana (para --screenshot-at)
+    let mut clock: f32 = 0.0;
 
     // Main render loop
     while !window.window_should_close() {
-        // Música - siempre sonando
-        theme.update_stream();
+        let hud_visible = matches!(state, GameState::Playing1 | GameState::Playing2) && !automap.open && !console.open;
+        // --screenshot-at: con el HUD a la vista se guarda al dibujarlo y se cierra después del
+        // swap; si no (menús, mapa), se guarda la vista del framebuffer y se cierra ya
+        if let Some(at) = cli.screenshot_at
+            && clock >= at
+            && !exit_after_shot
+        {
+            if hud_visible {
+                hud_shot = Some(capture::screenshot_path(true));
+                exit_after_shot = true;
+            } else {
+                match capture::save_screenshot(&framebuffer) {
+                    Ok(path) => println!("[screenshot] {} a los {:.1}s", path, clock),
+                    Err(e) => println!("[screenshot] {}", e),
+                }
+                break;
+            }
+        }
+        clock += window.get_frame_time();
+
+        // F2: captura de la vista (sin HUD); Shift+F2: con el HUD, que se guarda al dibujarlo
+        if window.is_key_pressed(KeyboardKey::KEY_F2) {
+            let with_hud = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            if with_hud && hud_visible {
+                hud_shot = Some(capture::screenshot_path(true));
+            } else if with_hud {
+                println!("[screenshot] El HUD solo se captura jugando");
+            } else {
+                match capture::save_screenshot(&framebuffer) {
+                    Ok(path) => println!("[screenshot] {}", path),
+                    Err(e) => println!("[screenshot] {}", e),
+                }
+            }
+        }
+
+        // F3: depuración (panel de tiempos, luego vista cenital con rayos y colisiones)
+        if window.is_key_pressed(KeyboardKey::KEY_F3) {
+            debug_mode = debug_mode.next();
+            println!("[debug] {:?}", debug_mode);
+        }
+
+        // F11: pantalla completa (sin bordes). Si cambió el tamaño se rehace el framebuffer
+        if window.is_key_pressed(KeyboardKey::KEY_F11) {
+            window.toggle_borderless_windowed();
+            borderless = !borderless;
+        }
+        let screen_size = (window.get_screen_width(), window.get_screen_height());
+        if screen_size != window_size && screen_size.0 > 0 && screen_size.1 > 0 {
+            window_size = screen_size;
+            apply_settings(&settings, &mut player, &mut audio, &mut framebuffer, &mut texman.store, window_size);
+            println!("[window] {}x{} | render {}x{}", window_size.0, window_size.1, framebuffer.width, framebuffer.height);
+            // el tamaño en ventana se recuerda para el próximo inicio; el de pantalla completa no
+            if !borderless && !window.is_window_fullscreen() {
+                (settings.window_width, settings.window_height) = window_size;
+                if let Err(e) = settings.save() { println!("[settings] {}", e); }
+            }
+        }
+
+        // Música de la pantalla actual (con cruce al cambiar) y silencio con M
+        if window.is_key_pressed(KeyboardKey::KEY_M) && !automap.typing() && !console.open { audio.toggle_mute(); }
+        audio.play_music(match state {
+            GameState::Welcome | GameState::LevelPicker => "menu",
+            GameState::Playing1 => "level1",
+            GameState::Playing2 => "level2",
+            GameState::Paused => if current_level == 2 { "level2" } else { "level1" },
+            GameState::Lost => "lost",
+            GameState::Win => "win",
+        });
+        audio.update(window.get_frame_time());
 
         // Fade step (global)
         if fading {
@@ -335,7 +667,8 @@ pub fn main() {
         match state {
 
     GameState::Welcome => {
-        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.welcome, if fading { fade_t } else { 0.0 });
+        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.welcome, if fading { fade_t } else { 0.0 }, &[]);
+        if window.is_key_pressed(KeyboardKey::KEY_ESCAPE) { break; }
         if pressed_enter(&window) && !fading {
             println!("[state] Welcome -> LevelPicker (fade)");
             fading = true; fade_dir = 1.0; fade_t = 0.0; next_state = Some(GameState::LevelPicker);
@@ -344,20 +677,18 @@ pub fn main() {
     }
 
     GameState::LevelPicker => {
-        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.picker, if fading { fade_t } else { 0.0 });
+        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.picker, if fading { fade_t } else { 0.0 }, &picker_lines(&save, &leaderboard));
         if pressed_one(&window) {
-            let (gp, st) = start_level(1, block_size, &mut player, &mut maze, &mut coins, &mut coins_total);
-            gate_pos = gp; level_start = st;
-            _collected = 0; lost = false; prev_collected = 0; _picked_key = false;
-            println!("[state] Start Level 1 | coins_total={} | coins={} cells", coins_total, coins.len());
+            begin_level(&level_file(1), (fixed_seed, block_size), &mut player, (&mut maze, &mut world, &mut portals), (&mut texman, &mut fog, &mut automap), &mut run, &prefabs);
+            current_level = 1; current_file = level_file(1);
+            println!("[state] Start Level 1 | coins_total={} | entities={}", world.coins_total, world.entities.len());
             if !fading { fading = true; fade_dir = 1.0; fade_t = 0.0; next_state = Some(GameState::Playing1); }
             continue;
         }
         if pressed_two(&window) {
-            let (gp, st) = start_level(2, block_size, &mut player, &mut maze, &mut coins, &mut coins_total);
-            gate_pos = gp; level_start = st;
-            _collected = 0; lost = false; prev_collected = 0; _picked_key = false;
-            println!("[state] Start Level 2 | coins_total={} | coins={} cells", coins_total, coins.len());
+            begin_level(&level_file(2), (fixed_seed, block_size), &mut player, (&mut maze, &mut world, &mut portals), (&mut texman, &mut fog, &mut automap), &mut run, &prefabs);
+            current_level = 2; current_file = level_file(2);
+            println!("[state] Start Level 2 | coins_total={} | entities={}", world.coins_total, world.entities.len());
             if !fading { fading = true; fade_dir = 1.0; fade_t = 0.0; next_state = Some(GameState::Playing2); }
             continue;
         }
@@ -370,60 +701,296 @@ pub fn main() {
     }
 
     GameState::Playing1 | GameState::Playing2 => {
+        // ` despliega la consola; mientras está abierta el juego queda en pausa
+        // (la ` sigue apretada en este frame, así que la entrada se lee desde el siguiente)
+        let console_opened = !console.open && !automap.typing() && window.is_key_pressed(KeyboardKey::KEY_GRAVE);
+        if console_opened {
+            console.toggle();
+            while window.get_char_pressed().is_some() {} // la ` no entra en la línea
+            audio.stop("siren");
+        }
+        if console.open {
+            if !console_opened && let Some(line) = console.handle_input(&mut window) {
+                let mut ctx = Context {
+                    player: &mut player,
+                    world: &mut world,
+                    maze: &maze,
+                    block_size,
+                    settings: &mut settings,
+                    texman: &mut texman,
+                    level_total: &mut run.total,
+                    level_elapsed: run.elapsed,
+                    run_stats: &mut run.stats,
+                    level_request: None,
+                    settings_changed: false,
+                };
+                console.execute(&line, &mut ctx);
+                let (level_request, settings_changed) = (ctx.level_request, ctx.settings_changed);
+                if settings_changed {
+                    apply_settings(&settings, &mut player, &mut audio, &mut framebuffer, &mut texman.store, window_size);
+                }
+                match level_request.map(|name| (resolve_level(&name), name)) {
+                    Some((Some((level, file)), _)) => {
+                        begin_level(&file, (fixed_seed, block_size), &mut player, (&mut maze, &mut world, &mut portals), (&mut texman, &mut fog, &mut automap), &mut run, &prefabs);
+                        current_level = level; current_file = file;
+                        println!("[state] Console -> Start {} | coins_total={} | entities={}", current_file, world.coins_total, world.entities.len());
+                        state = playing_state(level);
+                    }
+                    Some((None, name)) => console.error(&format!("level: no existe el nivel {}", name)),
+                    None => {}
+                }
+            }
+            framebuffer.upload(&mut window, &raylib_thread);
+            console.draw((&mut window, &raylib_thread), &framebuffer);
+            continue;
+        }
+
+        // Tab abre el mapa completo (con el mouse suelto para marcar); Tab o Esc lo cierran
+        let mut map_closed = false;
+        if !automap.open {
+            if window.is_key_pressed(KeyboardKey::KEY_TAB) {
+                automap.toggle();
+                window.enable_cursor();
+                if settings.automap_pauses { audio.stop("siren"); }
+            }
+        } else if automap.handle_input(&mut window) {
+            automap.toggle();
+            window.disable_cursor();
+            map_closed = true;
+        }
+        if automap.open && settings.automap_pauses {
+            let time_left = run.total.saturating_sub(run.elapsed).as_secs() as u32;
+            automap.draw((&mut window, &raylib_thread), &framebuffer, (&maze, &fog, block_size), &player, &world, (time_left, true));
+            continue;
+        }
+
+        // Esc pausa: se suelta el mouse y se congela el tiempo del nivel
+        if !automap.open && !map_closed && window.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            println!("[state] Playing -> Paused");
+            state = GameState::Paused;
+            pause_menu.open();
+            window.enable_cursor();
+            audio.stop("siren");
+            // se dibuja ya el menú: raylib lee el teclado al terminar el frame, y sin esto
+            // el menú vería el mismo Esc en el frame siguiente y se cerraría
+            pause_menu.draw(&mut window, &raylib_thread, &framebuffer, &settings);
+            continue;
+        }
+
         // Render loop
         framebuffer.clear();
 
-        process_events(&mut player, &window, &maze, block_size);
+        // Entrada y dt del frame: de la repetición si hay una, si no del teclado/mouse
+        // con --capture el juego avanza con paso fijo, sin importar cuánto tardó el frame
+        let live_dt = capture.as_ref().map_or_else(|| window.get_frame_time(), |c| c.step);
+        let (input, dt) = match replay.as_mut().map(|r| r.next_frame()) {
+            Some(Some(frame)) => (frame.input, frame.dt),
+            Some(None) => {
+                println!("[replay] Fin de la repetición, sigue el control normal");
+                replay = None;
+                (InputFrame::read(&window, &player), live_dt)
+            }
+            None => (InputFrame::read(&window, &player), live_dt),
+        };
+        // con el mapa abierto el mouse no gira la cámara, y escribiendo una nota no se mueve
+        let input = match (replay.is_some(), automap.open, automap.typing()) {
+            (false, _, true) => InputFrame::default(),
+            (false, true, false) => InputFrame { mouse_turn: 0.0, ..input },
+            _ => input,
+        };
+        if let Some(recording) = recorder.as_mut() {
+            if run.fresh { *recording = Replay::new(&current_file, recording.seed); }
+            recording.push(ReplayFrame { dt, input });
+        }
+        run.fresh = false;
+
+        process_events(&mut player, &input, &maze, &portals, block_size);
+
+        // Zoom del minimapa giratorio (- / +); se guarda con las opciones
+        let zoom_delta = if window.is_key_pressed(KeyboardKey::KEY_MINUS) || window.is_key_pressed(KeyboardKey::KEY_KP_SUBTRACT) {
+            minimap::ZOOM_STEP
+        } else if window.is_key_pressed(KeyboardKey::KEY_EQUAL) || window.is_key_pressed(KeyboardKey::KEY_KP_ADD) {
+            -minimap::ZOOM_STEP
+        } else {
+            0.0
+        };
+        if zoom_delta != 0.0 && !automap.open {
+            settings.minimap_zoom = (settings.minimap_zoom + zoom_delta).clamp(minimap::MIN_ZOOM, minimap::MAX_ZOOM);
+            if let Err(e) = settings.save() { println!("[settings] {}", e); }
+        }
+
+        // Tag de aerosol (T) en la pared que está al frente, si se alcanza
+        if input.spray {
+            let hit = cast_ray(&maze, &portals, &player, player.angle, block_size, None);
+            if let Some(cell) = hit.cell && hit.surface_distance == hit.hit_distance && hit.hit_distance < SPRAY_REACH * block_size as f32 {
+                texman.decals.spray(cell, hit.face, hit.texture_coord);
+            }
+        }
 
-        let _collected = pickup_coins(&player, &mut coins, block_size);
+        // Sistemas de entidades: IA, animaciones, recolección y triggers
+        let listener = Listener { maze: &maze, portals: &portals, player: &player, block_size };
+        for event in world.update(dt, &player, &maze, block_size) {
+            match event {
+                WorldEvent::Picked(PickupEffect::Coin { bonus_seconds }, at) => {
+                    audio.play_at("coin", &listener, at, &sfx_params);
+                    run.total += Duration::from_secs_f32(bonus_seconds);
+                }
+                WorldEvent::Picked(PickupEffect::TimeBonus { seconds }, at) => {
+                    audio.play_at("key", &listener, at, &sfx_params);
+                    // no cuenta como objeto en el puntaje: ya suma por los segundos que deja
+                    run.total += Duration::from_secs_f32(seconds);
+                }
+                // los efectos con duración ya quedaron activos en world.effects
+                WorldEvent::Picked(effect, at) => {
+                    audio.play_at("key", &listener, at, &sfx_params);
+                    println!("[effect] {:?}", effect);
+                    run.stats.pickups += 1;
+                }
+                WorldEvent::Triggered(TriggerEvent::Siren, at) => {
+                    if !run.lost { audio.play_at("police", &listener, at, &sfx_params); }
+                    run.stats.sirens += 1;
+                }
+                WorldEvent::Triggered(TriggerEvent::Caught, _) => {
+                    if !run.lost && !player.god {
+                        run.lost = true; audio.play("police"); audio.stop("siren");
+                        println!("[state] Caught by police -> Lost");
+                        state = GameState::Lost;
+                    }
+                }
+                WorldEvent::Triggered(TriggerEvent::Exit, at) => {
+                    if !run.lost {
+                        audio.play_at("key", &listener, at, &sfx_params);
+                        audio.stop("siren");
+                        println!("[state] Gate reached with all keys -> Win");
+                        state = GameState::Win;
+                        // los niveles sueltos (nivel 0, --level con archivo) y las partidas con trucos
+                        // de la consola no guardan récords ni entran a la tabla
+                        let cheated = run.stats.cheated || player.noclip || player.god;
+                        if cheated { println!("[save] Console cheats used: run not recorded"); }
+                        let counts = current_level > 0 && !cheated;
+                        if counts {
+                            if save.record_win(current_level, run.elapsed.as_secs_f32()) {
+                                println!("[save] New best time for level {}: {:.2}s", current_level, run.elapsed.as_secs_f32());
+                            }
+                            if let Err(e) = save.save() { println!("[save] {}", e); }
+                        }
+                        let time_left = run.total.saturating_sub(run.elapsed).as_secs() as u32;
+                        let score = Score::new(current_level, time_left, run.elapsed.as_secs_f32(), &run.stats);
+                        println!("[score] Level {} | {} pts", current_level, score.total);
+                        win_screen = counts.then(|| WinScreen::new(score, &leaderboard));
+                    }
+                }
+            }
+        }
 
-        if _collected > prev_collected {
-            let gained = (_collected - prev_collected) as u64;
-            coin_snd.play();
-            level_total += Duration::from_secs(5 * gained);
-            prev_collected = _collected;
+        // Sirena en bucle desde la patrulla que más se oye
+        if matches!(state, GameState::Playing1 | GameState::Playing2) {
+            audio.update_loop("siren", &listener, &world.emitters("siren"));
         }
 
-        let elapsed = level_start.elapsed();
-        let time_left = if elapsed >= level_total { 0 } else { (level_total - elapsed).as_secs() as u32 };
-        if time_left == 0 && !lost {
-            lost = true; police_snd.play();
+        player.speed_multiplier = world.effects.speed_multiplier();
+        texman.update(dt);
+        postfx.update(dt);
+
+        run.elapsed += Duration::from_secs_f32(dt);
+        let time_left = run.total.saturating_sub(run.elapsed).as_secs() as u32;
+        if time_left == 0 && !run.lost && !player.god {
+            run.lost = true; audio.play("police"); audio.stop("siren");
             println!("[state] Time up -> Lost");
             state = GameState::Lost;
         }
 
-        if !lost && _collected < coins_total {
-            let dx = player.position.x - gate_pos.0;
-            let dy = player.position.y - gate_pos.1;
-            let d = (dx*dx + dy*dy).sqrt();
-            if d < (block_size as f32 * 1.1) && last_police.elapsed() > Duration::from_millis(800) {
-                police_snd.play(); last_police = Instant::now();
+        // mundo, sprites, minimapa, HUD
+        let billboards = world.billboards();
+        let hits = if debug_mode == DebugMode::TopDown {
+            // la vista cenital reemplaza a la 3D: solo se lanzan los rayos (y se miden igual)
+            let start = Instant::now();
+            let hits = cast_view((&maze, &portals, block_size), &player, 0..framebuffer.width, framebuffer.width);
+            timings.rays = start.elapsed();
+            timings.sprites = Duration::ZERO;
+            hits
+        } else {
+            render_view(&mut framebuffer, (&maze, &portals, block_size), &player, &texman, (&billboards, &spriteman), settings.render_thread_count(), &mut timings)
+        };
+        fog.reveal_view(&player, &hits, block_size);
+        automap.track(&player, &world, &fog, block_size);
+        if debug_mode == DebugMode::TopDown {
+            render_debug_view(&mut framebuffer, (&maze, block_size), &player, &billboards, &hits);
+        }
+        // post-proceso solo sobre la vista (el minimapa y el HUD se dibujan después, sin efectos)
+        let police_distance = world.police_positions().iter()
+            .map(|(x, y)| (x - player.position.x).hypot(y - player.position.y) / block_size as f32)
+            .reduce(f32::min);
+        let frame_info = FrameInfo { time_left: run.total.saturating_sub(run.elapsed).as_secs_f32(), police_distance };
+        let post_start = Instant::now();
+        if debug_mode != DebugMode::TopDown { postfx.apply(&mut framebuffer, &frame_info); }
+        timings.post = post_start.elapsed();
+        let minimap_start = Instant::now();
+        // en la vista cenital el minimapa sobra
+        if settings.show_minimap && debug_mode != DebugMode::TopDown {
+            let scale = settings.resolution_scale;
+            if settings.minimap_rotating {
+                let radius = (90.0 * scale) as i32;
+                let margin = (16.0 * scale) as i32;
+                let center = (margin + radius, margin + radius);
+                render_minimap_rotating(&mut framebuffer, (&maze, &fog, block_size), &player, &world, (center, radius), settings.minimap_zoom);
+            } else {
+                render_minimap(&mut framebuffer, (&maze, &fog, block_size), &player, &world, ((16.0 * scale) as i32, (16.0 * scale) as i32), 0.15 * scale);
+            }
+        }
+        timings.minimap = minimap_start.elapsed();
+        timings.upload = framebuffer.upload_time;
+        if let Some(capture) = capture.as_mut() { capture.frame(&framebuffer); }
+        if automap.open {
+            // el mapa tapa el HUD; el juego sigue corriendo detrás
+            framebuffer.upload(&mut window, &raylib_thread);
+            automap.draw((&mut window, &raylib_thread), &framebuffer, (&maze, &fog, block_size), &player, &world, (time_left, false));
+        } else {
+            let debug_lines = if debug_mode == DebugMode::Off { Vec::new() } else { timings.lines(window.get_frame_time()) };
+            let screenshot = hud_shot.take();
+            let hud = Hud { effects: &world.effects.indicators(), debug_lines: &debug_lines, screenshot: screenshot.as_deref() };
+            framebuffer.swap_buffers(&mut window, &raylib_thread, world.coins_collected, world.coins_total, time_left, &hud);
+            if exit_after_shot && screenshot.is_some() {
+                println!("[screenshot] --screenshot-at a los {:.1}s", clock);
+                break;
             }
         }
+        continue;
+    }
 
-        // mundo, sprites, minimapa, HUD
-        let zbuf = render_world(&mut framebuffer, &maze, block_size, &player, &mut texman);
-        let dist_plane = (framebuffer.width as f32 / 2.0) / (player.field_of_view / 2.0).tan();
-        render_sprites(&mut framebuffer, &player, &mut coins, gate_pos, _collected, coins_total, &mut spriteman, block_size, dist_plane, &zbuf);
-        render_minimap(&mut framebuffer, &maze, block_size, &player, &coins, (16, 16), 0.15);
-        framebuffer.swap_buffers(&mut window, &raylib_thread, _collected, coins_total, time_left);
-
-        // Tocar llave para ganar
-        if _collected >= coins_total {
-            let dx = player.position.x - gate_pos.0;
-            let dy = player.position.y - gate_pos.1;
-            let d = (dx*dx + dy*dy).sqrt();
-            if d < (block_size as f32 * 0.7) {
-                key_snd.play();
-                println!("[state] Gate reached with all keys -> Win");
-                state = GameState::Win;
+    GameState::Paused => {
+        let action = pause_menu.handle_input(&window, &mut settings);
+        pause_menu.draw(&mut window, &raylib_thread, &framebuffer, &settings);
+        match action {
+            PauseAction::None => {}
+            PauseAction::SettingsChanged => {
+                apply_settings(&settings, &mut player, &mut audio, &mut framebuffer, &mut texman.store, window_size);
+                postfx = PostProcess::from_settings(&settings);
+                if let Err(e) = settings.save() { println!("[settings] {}", e); }
+            }
+            PauseAction::Resume => {
+                println!("[state] Paused -> Playing");
+                state = playing_state(current_level);
+                window.disable_cursor();
+            }
+            PauseAction::Restart => {
+                println!("[state] Paused -> Restart {}", current_file);
+                begin_level(&current_file, (fixed_seed, block_size), &mut player, (&mut maze, &mut world, &mut portals), (&mut texman, &mut fog, &mut automap), &mut run, &prefabs);
+                state = playing_state(current_level);
+                window.disable_cursor();
+            }
+            PauseAction::QuitToMenu => {
+                println!("[state] Paused -> LevelPicker (fade in)");
+                window.disable_cursor();
+                state = GameState::LevelPicker;
+                fading = true; fade_dir = -1.0; fade_t = 1.0; next_state = None;
             }
         }
         continue;
     }
 
     GameState::Lost => {
-        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.lost, if fading { fade_t } else { 0.0 });
+        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.lost, if fading { fade_t } else { 0.0 }, &[]);
         if pressed_enter(&window) {
             println!("[state] Lost -> LevelPicker (fade)");
             if !fading { fading = true; fade_dir = 1.0; fade_t = 0.0; next_state = Some(GameState::LevelPicker); }
@@ -432,8 +999,18 @@ pub fn main() {
     }
 
     GameState::Win => {
-        draw_fullscreen_screen(&mut window, &raylib_thread, &screens.win, if fading { fade_t } else { 0.0 });
-        if pressed_enter(&window) {
+        let proceed = match win_screen.as_mut() {
+            Some(screen) => {
+                let proceed = screen.handle_input(&mut window, &mut leaderboard);
+                screen.draw(&mut window, &raylib_thread, &screens.win, &leaderboard, if fading { fade_t } else { 0.0 });
+                proceed
+            }
+            None => {
+                draw_fullscreen_screen(&mut window, &raylib_thread, &screens.win, if fading { fade_t } else { 0.0 }, &[]);
+                pressed_enter(&window)
+            }
+        };
+        if proceed {
             println!("[state] Win -> Welcome (fade)");
             if !fading { fading = true; fade_dir = 1.0; fade_t = 0.0; next_state = Some(GameState::Welcome); }
         }
@@ -441,4 +1018,16 @@ pub fn main() {
     }
 }
     }
+
+    if let (Some(dir), Some(capture)) = (cli.capture.as_ref(), capture.as_ref()) {
+        println!("[capture] {} frames guardados en {}", capture.saved(), dir);
+    }
+
+    // --record: guarda la última partida jugada
+    if let (Some(path), Some(recording)) = (cli.record.as_ref(), recorder.as_ref()) {
+        match recording.save(path) {
+            Ok(()) => println!("[replay] Grabación guardada en {} ({} frames)", path, recording.frame_count()),
+            Err(e) => println!("[replay] {}", e),
+        }
+    }
 }
\ No newline at end of file
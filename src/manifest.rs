@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use raylib::prelude::Color;
+use crate::caster::Face;
+use crate::procedural::Pattern;
+
+/// Cómo se mezcla una capa con lo que tiene debajo
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BlendMode {
+    Alpha,    // se pinta encima según su alpha
+    Add,      // suma (brillos, neón)
+    Multiply, // oscurece (manchas, suciedad)
+    Screen,   // aclara sin quemar
+}
+
+impl BlendMode {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "alpha" => Some(BlendMode::Alpha),
+            "add" => Some(BlendMode::Add),
+            "multiply" => Some(BlendMode::Multiply),
+            "screen" => Some(BlendMode::Screen),
+            _ => None,
+        }
+    }
+}
+
+/// Imagen que se compone encima de la base
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layer {
+    pub path: String,
+    pub blend: BlendMode,
+    pub tint: Color,
+}
+
+/// De dónde salen los píxeles de un frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    File(String),
+    Procedural(Pattern, Color, Color), // patrón, color principal y secundario
+}
+
+/// Un frame de una textura: su imagen y el tinte que se le aplica
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub source: Source,
+    pub tint: Color,
+}
+
+// Color no implementa Hash: se hashean sus bytes (las cachés de texturas usan estos tipos de clave)
+fn hash_color<H: Hasher>(color: &Color, state: &mut H) {
+    [color.r, color.g, color.b, color.a].hash(state);
+}
+
+impl Hash for Layer {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        self.blend.hash(state);
+        hash_color(&self.tint, state);
+    }
+}
+
+impl Hash for Source {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Source::File(path) => path.hash(state),
+            Source::Procedural(pattern, main, second) => {
+                pattern.hash(state);
+                hash_color(main, state);
+                hash_color(second, state);
+            }
+        }
+    }
+}
+
+impl Hash for Frame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+        hash_color(&self.tint, state);
+    }
+}
+
+/// Textura con nombre: uno o más frames (animación a `fps`), las capas que se componen
+/// encima de cada frame y un desplazamiento continuo en (u, v) por segundo
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureDef {
+    pub frames: Vec<Frame>,
+    pub layers: Vec<Layer>,
+    pub fps: f32,
+    pub scroll: (f32, f32),
+}
+
+impl TextureDef {
+    fn new(frame: Frame) -> Self {
+        Self { frames: vec![frame], layers: Vec::new(), fps: 0.0, scroll: (0.0, 0.0) }
+    }
+}
+
+/// A qué paredes se le asigna una textura
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Target {
+    Char(char),                        // todas las celdas con ese símbolo
+    Cell(usize, usize, Option<Face>),  // una celda (x, y), opcionalmente solo una de sus caras
+    Default,                           // lo que no tenga otra asignación
+}
+
+/// Manifiesto de texturas. Una instrucción por línea; las que empiezan con '#' son comentarios.
+///
+/// ```text
+/// texture <nombre> <archivo> [tinte]
+/// procedural <nombre> <bricks|tiles|concrete> [color] [color2]
+/// frame <nombre> <archivo> [tinte]
+/// fps <nombre> <frames por segundo>
+/// scroll <nombre> <u por segundo> <v por segundo>
+/// layer <nombre> <archivo> [alpha|add|multiply|screen] [tinte]
+/// char <símbolo> <nombre>
+/// cell <x> <y> [n|s|e|w] <nombre>
+/// default <nombre>
+/// decal <archivo> [tinte]
+/// decal_chance <0 a 1>
+/// ```
+///
+/// El tinte es `r,g,b` o `r,g,b,a` (0 a 255) y multiplica los colores de la imagen.
+/// `frame` agrega frames de animación a una textura (el primero es el de `texture`) y
+/// `scroll` la desplaza con el tiempo, para letreros de neón, luces y anuncios.
+/// Los `decal` forman el pool de graffiti que se reparte por las caras de las paredes.
+pub struct Manifest {
+    pub textures: HashMap<String, TextureDef>,
+    pub mappings: Vec<(Target, String)>,
+    pub decals: Vec<Frame>,
+    pub decal_chance: Option<f32>, // probabilidad de graffiti por cara
+}
+
+impl Manifest {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+        Self::parse(&text, path)
+    }
+
+    /// `source` solo se usa para los mensajes de error
+    pub fn parse(text: &str, source: &str) -> Result<Self, String> {
+        let mut manifest = Manifest { textures: HashMap::new(), mappings: Vec::new(), decals: Vec::new(), decal_chance: None };
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let err = |msg: &str| format!("{}:{}: {}", source, number + 1, msg);
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts[..] {
+                ["texture", name, base, ref rest @ ..] => {
+                    let tint = match rest {
+                        [] => Color::WHITE,
+                        [tint] => parse_tint(tint).ok_or_else(|| err("tinte inválido"))?,
+                        _ => return Err(err("sobran valores en texture")),
+                    };
+                    let frame = Frame { source: Source::File(base.to_string()), tint };
+                    manifest.textures.insert(name.to_string(), TextureDef::new(frame));
+                }
+                ["procedural", name, pattern, ref rest @ ..] => {
+                    let pattern = Pattern::parse(pattern).ok_or_else(|| err("patrón inválido (bricks, tiles, concrete)"))?;
+                    let (mut primary, mut secondary) = pattern.default_colors();
+                    match rest {
+                        [] => {}
+                        [a] => primary = parse_tint(a).ok_or_else(|| err("color inválido"))?,
+                        [a, b] => {
+                            primary = parse_tint(a).ok_or_else(|| err("color inválido"))?;
+                            secondary = parse_tint(b).ok_or_else(|| err("color inválido"))?;
+                        }
+                        _ => return Err(err("sobran valores en procedural")),
+                    }
+                    let frame = Frame { source: Source::Procedural(pattern, primary, secondary), tint: Color::WHITE };
+                    manifest.textures.insert(name.to_string(), TextureDef::new(frame));
+                }
+                ["frame", name, path, ref rest @ ..] => {
+                    let tint = match rest {
+                        [] => Color::WHITE,
+                        [tint] => parse_tint(tint).ok_or_else(|| err("tinte inválido"))?,
+                        _ => return Err(err("sobran valores en frame")),
+                    };
+                    let def = manifest.textures.get_mut(name).ok_or_else(|| err("frame antes de su texture"))?;
+                    def.frames.push(Frame { source: Source::File(path.to_string()), tint });
+                }
+                ["fps", name, fps] => {
+                    let fps: f32 = fps.parse().map_err(|_| err("fps inválido"))?;
+                    let def = manifest.textures.get_mut(name).ok_or_else(|| err("fps antes de su texture"))?;
+                    def.fps = fps.max(0.0);
+                }
+                ["scroll", name, u, v] => {
+                    let u = u.parse().map_err(|_| err("velocidad u inválida"))?;
+                    let v = v.parse().map_err(|_| err("velocidad v inválida"))?;
+                    let def = manifest.textures.get_mut(name).ok_or_else(|| err("scroll antes de su texture"))?;
+                    def.scroll = (u, v);
+                }
+                ["layer", name, path, ref rest @ ..] => {
+                    let mut layer = Layer { path: path.to_string(), blend: BlendMode::Alpha, tint: Color::WHITE };
+                    for value in rest {
+                        if let Some(blend) = BlendMode::parse(value) {
+                            layer.blend = blend;
+                        } else {
+                            layer.tint = parse_tint(value).ok_or_else(|| err("modo o tinte inválido"))?;
+                        }
+                    }
+                    let def = manifest.textures.get_mut(name).ok_or_else(|| err("layer antes de su texture"))?;
+                    def.layers.push(layer);
+                }
+                ["char", symbol, name] => {
+                    let mut chars = symbol.chars();
+                    let (Some(ch), None) = (chars.next(), chars.next()) else { return Err(err("el símbolo debe ser un solo carácter")) };
+                    manifest.mappings.push((Target::Char(ch), name.to_string()));
+                }
+                ["cell", x, y, ref rest @ ..] => {
+                    let x = x.parse().map_err(|_| err("x inválida"))?;
+                    let y = y.parse().map_err(|_| err("y inválida"))?;
+                    let (face, name) = match rest {
+                        [name] => (None, name),
+                        [face, name] => (Some(parse_face(face).ok_or_else(|| err("cara inválida (n, s, e, w)"))?), name),
+                        _ => return Err(err("se esperaba cell <x> <y> [cara] <nombre>")),
+                    };
+                    manifest.mappings.push((Target::Cell(x, y, face), name.to_string()));
+                }
+                ["default", name] => manifest.mappings.push((Target::Default, name.to_string())),
+                ["decal", path, ref rest @ ..] => {
+                    let tint = match rest {
+                        [] => Color::WHITE,
+                        [tint] => parse_tint(tint).ok_or_else(|| err("tinte inválido"))?,
+                        _ => return Err(err("sobran valores en decal")),
+                    };
+                    manifest.decals.push(Frame { source: Source::File(path.to_string()), tint });
+                }
+                ["decal_chance", chance] => {
+                    let chance: f32 = chance.parse().ok().filter(|c: &f32| !c.is_nan()).ok_or_else(|| err("probabilidad inválida"))?;
+                    manifest.decal_chance = Some(chance.clamp(0.0, 1.0));
+                }
+                _ => return Err(err("instrucción desconocida")),
+            }
+        }
+
+        // cada asignación tiene que apuntar a una textura definida
+        for (_, name) in &manifest.mappings {
+            if !manifest.textures.contains_key(name) {
+                return Err(format!("{}: textura no definida: {}", source, name));
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+fn parse_tint(text: &str) -> Option<Color> {
+    let values: Vec<u8> = text.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+    match values[..] {
+        [r, g, b] => Some(Color::new(r, g, b, 255)),
+        [r, g, b, a] => Some(Color::new(r, g, b, a)),
+        _ => None,
+    }
+}
+
+fn parse_face(text: &str) -> Option<Face> {
+    match text {
+        "n" => Some(Face::North),
+        "s" => Some(Face::South),
+        "e" => Some(Face::East),
+        "w" => Some(Face::West),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_textures_layers_and_mappings() {
+        let text = "
+# comentario
+texture brick bricks.png 200,180,160
+frame brick bricks2.png
+fps brick 4
+scroll brick 0.5 -1
+layer brick grime.png multiply 10,20,30,40
+procedural wall tiles 255,255,255
+char # brick
+cell 3 4 n wall
+default wall
+";
+        let manifest = Manifest::parse(text, "test").unwrap();
+        let brick = &manifest.textures["brick"];
+        assert_eq!(brick.frames.len(), 2);
+        assert_eq!(brick.frames[0], Frame { source: Source::File("bricks.png".into()), tint: Color::new(200, 180, 160, 255) });
+        assert_eq!((brick.fps, brick.scroll), (4.0, (0.5, -1.0)));
+        assert_eq!(brick.layers, vec![Layer { path: "grime.png".into(), blend: BlendMode::Multiply, tint: Color::new(10, 20, 30, 40) }]);
+        assert!(matches!(manifest.textures["wall"].frames[0].source, Source::Procedural(Pattern::Tiles, _, _)));
+        assert_eq!(manifest.mappings, vec![
+            (Target::Char('#'), "brick".to_string()),
+            (Target::Cell(3, 4, Some(Face::North)), "wall".to_string()),
+            (Target::Default, "wall".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn rejects_bad_lines_with_their_number() {
+        assert_eq!(Manifest::parse("\nfoo bar", "m.txt").err().as_deref(), Some("m.txt:2: instrucción desconocida"));
+        assert!(Manifest::parse("texture a a.png 300,0,0", "m").is_err());
+        assert!(Manifest::parse("frame a a.png", "m").is_err());
+        assert!(Manifest::parse("char ab x", "m").is_err());
+        assert!(Manifest::parse("cell 1 1 q x", "m").is_err());
+        assert!(Manifest::parse("default missing", "m").is_err());
+    }
+
+    #[test]
+    fn decal_chance_is_clamped() {
+        let chance = |value: &str| Manifest::parse(&format!("decal_chance {}", value), "m").map(|m| m.decal_chance);
+        assert_eq!(chance("0.3"), Ok(Some(0.3)));
+        assert_eq!(chance("2"), Ok(Some(1.0)));
+        assert_eq!(chance("-1"), Ok(Some(0.0)));
+        assert!(chance("NaN").is_err());
+        assert!(chance("mucho").is_err());
+    }
+}
@@ -4,11 +4,25 @@ use std::io::{BufRead, BufReader};
 // Representamos el laberinto como una matriz de caracteres
 pub type Maze = Vec<Vec<char>>;
 
-// Construccion del laberinto
-pub fn load_maze(filename: &str) -> Maze {
+// Directivas del nivel: lineas con la forma `@clave valor` (ej. `@coins 5`), en orden
+pub type Directives = Vec<(String, String)>;
+
+// Construccion del laberinto; las lineas que empiezan con '@' son directivas y no filas
+pub fn load_maze(filename: &str) -> (Maze, Directives) {
     let file = File::open(filename).expect("No se pudo abrir maze.txt");
     let reader = BufReader::new(file);
-    reader.lines().map(|l| l.unwrap().chars().collect()).collect()
+    let mut maze = Vec::new();
+    let mut directives = Vec::new();
+    for line in reader.lines() {
+        let line = line.unwrap();
+        if let Some(directive) = line.strip_prefix('@') {
+            let (key, value) = directive.split_once(' ').unwrap_or((directive, ""));
+            directives.push((key.trim().to_string(), value.trim().to_string()));
+        } else {
+            maze.push(line.chars().collect());
+        }
+    }
+    (maze, directives)
 }
 
 // Busqueda de un caracter en el laberinto, se usa para poner al player en p
@@ -0,0 +1,274 @@
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+use crate::settings::{Settings, SettingsItem};
+use crate::score::{Leaderboard, Score, ScoreEntry, sanitize_initials};
+
+const MAIN_ITEMS: [&str; 4] = ["Continuar", "Reiniciar nivel", "Opciones", "Salir al menu"];
+const ITEM_WIDTH: i32 = 560;
+const ITEM_HEIGHT: i32 = 48;
+const ITEM_GAP: i32 = 10;
+const FONT_SIZE: i32 = 26;
+
+#[derive(Clone, Copy, PartialEq)]
+enum PausePage {
+    Main,
+    Settings,
+}
+
+/// Lo que el juego tiene que hacer después de la entrada de este frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PauseAction {
+    None,
+    Resume,
+    Restart,
+    QuitToMenu,
+    SettingsChanged,
+}
+
+/// Menú de pausa con su página de opciones; se maneja con flechas/WASD + Enter o con el mouse
+pub struct PauseMenu {
+    page: PausePage,
+    selected: usize,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self { page: PausePage::Main, selected: 0 }
+    }
+
+    /// Vuelve a la primera opción de la página principal
+    pub fn open(&mut self) {
+        self.page = PausePage::Main;
+        self.selected = 0;
+    }
+
+    fn item_count(&self) -> usize {
+        match self.page {
+            PausePage::Main => MAIN_ITEMS.len(),
+            PausePage::Settings => SettingsItem::ALL.len() + 1, // + "Volver"
+        }
+    }
+
+    pub fn handle_input(&mut self, window: &RaylibHandle, settings: &mut Settings) -> PauseAction {
+        let count = self.item_count();
+
+        // Mouse: pasar por encima selecciona, clic izquierdo activa (o sube), derecho baja
+        let mouse = window.get_mouse_position();
+        let (screen_w, screen_h) = (window.get_screen_width(), window.get_screen_height());
+        let mut clicked = None;
+        for index in 0..count {
+            let (x, y, w, h) = item_rect(index, count, screen_w, screen_h);
+            let inside = mouse.x >= x as f32 && mouse.x < (x + w) as f32 && mouse.y >= y as f32 && mouse.y < (y + h) as f32;
+            if inside {
+                if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                    self.selected = index;
+                    clicked = Some(1);
+                } else if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+                    self.selected = index;
+                    clicked = Some(-1);
+                } else if window.get_mouse_delta().length() > 0.0 {
+                    self.selected = index;
+                }
+            }
+        }
+
+        // Teclado
+        if window.is_key_pressed(KeyboardKey::KEY_UP) || window.is_key_pressed(KeyboardKey::KEY_W) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_DOWN) || window.is_key_pressed(KeyboardKey::KEY_S) {
+            self.selected = (self.selected + 1) % count;
+        }
+        let back = window.is_key_pressed(KeyboardKey::KEY_ESCAPE) || window.is_key_pressed(KeyboardKey::KEY_BACKSPACE);
+        let confirm = window.is_key_pressed(KeyboardKey::KEY_ENTER) || window.is_key_pressed(KeyboardKey::KEY_SPACE);
+        let mut direction = clicked.unwrap_or(0);
+        if window.is_key_pressed(KeyboardKey::KEY_RIGHT) || window.is_key_pressed(KeyboardKey::KEY_D) || confirm { direction = 1; }
+        if window.is_key_pressed(KeyboardKey::KEY_LEFT) || window.is_key_pressed(KeyboardKey::KEY_A) { direction = -1; }
+
+        match self.page {
+            PausePage::Main => {
+                if back { return PauseAction::Resume; }
+                if clicked.is_none() && !confirm { return PauseAction::None; }
+                match self.selected {
+                    0 => PauseAction::Resume,
+                    1 => PauseAction::Restart,
+                    2 => {
+                        self.page = PausePage::Settings;
+                        self.selected = 0;
+                        PauseAction::None
+                    }
+                    _ => PauseAction::QuitToMenu,
+                }
+            }
+            PausePage::Settings => {
+                let back_selected = self.selected == SettingsItem::ALL.len();
+                if back || (back_selected && direction != 0) {
+                    self.page = PausePage::Main;
+                    self.selected = 2;
+                    return PauseAction::None;
+                }
+                if direction == 0 || back_selected { return PauseAction::None; }
+                settings.adjust(SettingsItem::ALL[self.selected], direction);
+                PauseAction::SettingsChanged
+            }
+        }
+    }
+
+    /// Dibuja el último frame del juego oscurecido y el menú encima
+    pub fn draw(&self, window: &mut RaylibHandle, raylib_thread: &RaylibThread, framebuffer: &Framebuffer, settings: &Settings) {
+        let mut renderer = window.begin_drawing(raylib_thread);
+        renderer.clear_background(Color::BLACK);
+        let screen_w = renderer.get_screen_width();
+        let screen_h = renderer.get_screen_height();
+        framebuffer.draw_to_screen(&mut renderer);
+        renderer.draw_rectangle(0, 0, screen_w, screen_h, Color::new(10, 12, 20, 170));
+
+        let title = if self.page == PausePage::Main { "PAUSA" } else { "OPCIONES" };
+        let title_size = 48;
+        let title_w = renderer.measure_text(title, title_size);
+        let (_, first_y, _, _) = item_rect(0, self.item_count(), screen_w, screen_h);
+        renderer.draw_text(title, (screen_w - title_w) / 2, first_y - title_size - 30, title_size, Color::RAYWHITE);
+
+        let labels: Vec<(String, Option<String>)> = match self.page {
+            PausePage::Main => MAIN_ITEMS.iter().map(|l| (l.to_string(), None)).collect(),
+            PausePage::Settings => SettingsItem::ALL.iter()
+                .map(|&item| (item.label().to_string(), Some(settings.value_text(item))))
+                .chain(std::iter::once(("Volver".to_string(), None)))
+                .collect(),
+        };
+
+        for (index, (label, value)) in labels.iter().enumerate() {
+            let (x, y, w, h) = item_rect(index, labels.len(), screen_w, screen_h);
+            let selected = index == self.selected;
+            let fill = if selected { Color::new(0, 218, 209, 220) } else { Color::new(10, 12, 20, 200) };
+            let text = if selected { Color::new(10, 12, 20, 255) } else { Color::RAYWHITE };
+            renderer.draw_rectangle(x, y, w, h, fill);
+            renderer.draw_rectangle_lines(x, y, w, h, Color::new(0, 218, 209, 210));
+            let text_y = y + (h - FONT_SIZE) / 2;
+            match value {
+                Some(value) => {
+                    renderer.draw_text(label, x + 16, text_y, FONT_SIZE, text);
+                    let value_label = format!("< {} >", value);
+                    let value_w = renderer.measure_text(&value_label, FONT_SIZE);
+                    renderer.draw_text(&value_label, x + w - value_w - 16, text_y, FONT_SIZE, text);
+                }
+                None => {
+                    let label_w = renderer.measure_text(label, FONT_SIZE);
+                    renderer.draw_text(label, x + (w - label_w) / 2, text_y, FONT_SIZE, text);
+                }
+            }
+        }
+    }
+}
+
+/// Pantalla de victoria: puntaje de la partida, iniciales si entra en la tabla y la tabla del nivel
+pub struct WinScreen {
+    score: Score,
+    initials: String,
+    entering: bool,      // todavía escribiendo iniciales
+    rank: Option<usize>, // lugar en la tabla una vez guardado
+}
+
+impl WinScreen {
+    pub fn new(score: Score, leaderboard: &Leaderboard) -> Self {
+        let entering = leaderboard.rank_for(score.level, score.total, score.elapsed).is_some();
+        Self { score, initials: String::new(), entering, rank: None }
+    }
+
+    /// Maneja la escritura de iniciales; devuelve true cuando el jugador quiere seguir
+    pub fn handle_input(&mut self, window: &mut RaylibHandle, leaderboard: &mut Leaderboard) -> bool {
+        if !self.entering {
+            return window.is_key_pressed(KeyboardKey::KEY_ENTER)
+                || window.is_key_pressed(KeyboardKey::KEY_KP_ENTER)
+                || window.is_key_pressed(KeyboardKey::KEY_SPACE);
+        }
+
+        while let Some(c) = window.get_char_pressed() {
+            if self.initials.len() < 3 {
+                self.initials = sanitize_initials(&format!("{}{}", self.initials, c));
+            }
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.initials.pop();
+        }
+        let confirm = window.is_key_pressed(KeyboardKey::KEY_ENTER) || window.is_key_pressed(KeyboardKey::KEY_KP_ENTER);
+        if confirm && !self.initials.is_empty() {
+            let entry = ScoreEntry { initials: self.initials.clone(), score: self.score.total, elapsed: self.score.elapsed };
+            self.rank = leaderboard.insert(self.score.level, entry);
+            self.entering = false;
+            if let Err(e) = leaderboard.save() { println!("[leaderboard] {}", e); }
+            println!("[leaderboard] Level {} | {} {} pts | rank {:?}", self.score.level, self.initials, self.score.total, self.rank.map(|r| r + 1));
+        }
+        false
+    }
+
+    pub fn draw(&self, window: &mut RaylibHandle, raylib_thread: &RaylibThread, background: &Texture2D, leaderboard: &Leaderboard, fade_t: f32) {
+        let mut renderer = window.begin_drawing(raylib_thread);
+        renderer.clear_background(Color::BLACK);
+        let screen_w = renderer.get_screen_width();
+        let screen_h = renderer.get_screen_height();
+        let source = Rectangle::new(0.0, 0.0, background.width as f32, background.height as f32);
+        let dest = Rectangle::new(0.0, 0.0, screen_w as f32, screen_h as f32);
+        renderer.draw_texture_pro(background, source, dest, Vector2::zero(), 0.0, Color::WHITE);
+
+        // Panel a la derecha
+        let panel_w = 460;
+        let panel_h = screen_h - 80;
+        let panel_x = screen_w - panel_w - 40;
+        let panel_y = 40;
+        renderer.draw_rectangle(panel_x, panel_y, panel_w, panel_h, Color::new(10, 12, 20, 210));
+        renderer.draw_rectangle_lines(panel_x, panel_y, panel_w, panel_h, Color::new(0, 218, 209, 210));
+
+        let x = panel_x + 24;
+        let mut y = panel_y + 20;
+        let score = &self.score;
+        renderer.draw_text(&format!("Nivel {} - {} pts", score.level, score.total), x, y, 32, Color::RAYWHITE);
+        y += 48;
+        let lines = [
+            format!("Tiempo restante {}s: {}", score.time_left, score.time_points),
+            format!("Objetos de poder: {}", score.pickup_points),
+            format!("Sin sirenas: {}", score.no_siren_bonus),
+        ];
+        for line in &lines {
+            renderer.draw_text(line, x, y, 22, Color::LIGHTGRAY);
+            y += 30;
+        }
+        y += 16;
+
+        if self.entering {
+            renderer.draw_text("Nuevo record! Escribe tus iniciales:", x, y, 22, Color::new(255, 219, 88, 255));
+            y += 34;
+            let shown = format!("{}{}", self.initials, "_".repeat(3 - self.initials.len()));
+            renderer.draw_text(&shown, x, y, 40, Color::RAYWHITE);
+            y += 56;
+            renderer.draw_text("Enter para guardar", x, y, 20, Color::LIGHTGRAY);
+            y += 40;
+        } else {
+            renderer.draw_text("Enter para continuar", x, y, 22, Color::LIGHTGRAY);
+            y += 40;
+        }
+
+        renderer.draw_text("Mejores puntajes", x, y, 26, Color::RAYWHITE);
+        y += 36;
+        for (index, entry) in leaderboard.entries(score.level).iter().enumerate() {
+            let highlight = self.rank == Some(index);
+            let color = if highlight { Color::new(255, 219, 88, 255) } else { Color::RAYWHITE };
+            let line = format!("{:>2}. {:<3} {:>6}  {:.1}s", index + 1, entry.initials, entry.score, entry.elapsed);
+            renderer.draw_text(&line, x, y, 22, color);
+            y += 28;
+        }
+
+        if fade_t > 0.0 {
+            let a = (fade_t.clamp(0.0, 1.0) * 255.0) as u8;
+            renderer.draw_rectangle(0, 0, screen_w, screen_h, Color::new(0, 0, 0, a));
+        }
+    }
+}
+
+/// Rectángulo (x, y, ancho, alto) de la opción `index` de `count`, centradas en pantalla
+fn item_rect(index: usize, count: usize, screen_w: i32, screen_h: i32) -> (i32, i32, i32, i32) {
+    let total_h = count as i32 * (ITEM_HEIGHT + ITEM_GAP) - ITEM_GAP;
+    let x = (screen_w - ITEM_WIDTH) / 2;
+    let y = (screen_h - total_h) / 2 + 30 + index as i32 * (ITEM_HEIGHT + ITEM_GAP);
+    (x, y, ITEM_WIDTH, ITEM_HEIGHT)
+}
@@ -0,0 +1,246 @@
+use raylib::prelude::*;
+use crate::caster::{Intersect, column_angle};
+use crate::effects::EffectKind;
+use crate::entities::World;
+use crate::framebuffer::Framebuffer;
+use crate::maze::{Maze, is_walkable};
+use crate::player::Player;
+
+/// Fondo de las celdas vistas (teal más claro)
+const FLOOR_COLOR: Color = Color::new(60, 190, 180, 230);
+/// Celdas que el jugador todavía no vio
+const FOG_COLOR: Color = Color::new(20, 38, 50, 255);
+/// Borde (navy)
+const BORDER_COLOR: Color = Color::new(24, 32, 56, 255);
+/// Celdas de radio que se ven en el minimapa giratorio: mínimo, máximo y paso del zoom
+pub const MIN_ZOOM: f32 = 2.0;
+pub const MAX_ZOOM: f32 = 10.0;
+pub const ZOOM_STEP: f32 = 1.0;
+/// Cada cuántas columnas de la vista se usa un rayo para descubrir celdas
+const FOG_RAY_STRIDE: usize = 4;
+
+// Colores para el minimap
+pub fn cell_to_color(cell: char) -> Color {
+    match cell {
+        '+' => Color::new(90, 140, 255, 255),   // azul eléctrico (más visible)
+        '-' => Color::new(0, 218, 209, 255),    // cian vibrante
+        '|' => Color::new(255, 120, 190, 255),  // rosa brillante
+        'g' => Color::new(255, 219, 88, 255),   // dorado cálido
+        'M' => Color::new(200, 225, 240, 255),  // espejo: gris plateado
+        'O' => Color::new(170, 90, 255, 255),   // portal: violeta
+        _ => Color::LIGHTGRAY,
+    }
+}
+
+/// Celdas del nivel que el jugador ya vio (niebla de guerra del minimapa). Se descubren
+/// con los rayos de la vista 3D: todo lo que cruza un rayo hasta su primera pared.
+pub struct Fog {
+    seen: Vec<Vec<bool>>,
+}
+
+impl Fog {
+    pub fn new(maze: &Maze) -> Self {
+        Self { seen: maze.iter().map(|row| vec![false; row.len()]).collect() }
+    }
+
+    pub fn is_seen(&self, (x, y): (usize, usize)) -> bool {
+        self.seen.get(y).and_then(|row| row.get(x)).copied().unwrap_or(false)
+    }
+
+    /// Descubre lo que recorre un rayo desde `from` por `distance` píxeles (incluida la pared)
+    pub fn reveal_ray(&mut self, (x, y): (f32, f32), angle: f32, distance: f32, block_size: usize) {
+        let step = block_size as f32 * 0.25;
+        let (dx, dy) = (angle.cos(), angle.sin());
+        let mut t = 0.0;
+        while t <= distance + step {
+            let (px, py) = (x + dx * t, y + dy * t);
+            if px < 0.0 || py < 0.0 { break; }
+            let (cx, cy) = (px as usize / block_size, py as usize / block_size);
+            match self.seen.get_mut(cy).and_then(|row| row.get_mut(cx)) {
+                Some(seen) => *seen = true,
+                None => break,
+            }
+            t += step;
+        }
+    }
+
+    /// Descubre lo que alcanzan los rayos de la vista (uno por columna), hasta la primera superficie
+    pub fn reveal_view(&mut self, player: &Player, hits: &[Intersect], block_size: usize) {
+        let from = (player.position.x, player.position.y);
+        for (i, hit) in hits.iter().enumerate().step_by(FOG_RAY_STRIDE) {
+            let angle = column_angle(player, i as u32, hits.len() as u32);
+            self.reveal_ray(from, angle, hit.surface_distance, block_size);
+        }
+    }
+}
+
+/// Mapa 2D de todo el laberinto, fijo, con las celdas no vistas tapadas por la niebla
+/// (mientras dura MapReveal se ve todo)
+pub fn render_minimap(
+    framebuffer: &mut Framebuffer,
+    (maze, fog, block_size): (&Maze, &Fog, usize),
+    player: &Player,
+    world: &World,
+    origin: (i32, i32),
+    scale: f32,
+) {
+    let mini = ((block_size as f32) * scale) as usize;
+    let (ox, oy) = origin;
+    let revealed = world.effects.is_active(EffectKind::MapReveal);
+
+    let mw = (maze[0].len() * mini) as i32;
+    let mh = (maze.len() * mini) as i32;
+    // fondo del minimapa
+    framebuffer.set_current_color(FLOOR_COLOR);
+    framebuffer.fill_rect(ox, oy, mw, mh);
+    // borde fino
+    framebuffer.set_current_color(BORDER_COLOR);
+    framebuffer.fill_rect(ox - 1, oy - 1, mw + 2, 1);
+    framebuffer.fill_rect(ox - 1, oy + mh, mw + 2, 1);
+    framebuffer.fill_rect(ox - 1, oy - 1, 1, mh + 2);
+    framebuffer.fill_rect(ox + mw, oy - 1, 1, mh + 2);
+
+    for (row_index, row) in maze.iter().enumerate() {
+        for (col_index, &cell) in row.iter().enumerate() {
+            let xo = col_index * mini;
+            let yo = row_index * mini;
+            if revealed || fog.is_seen((col_index, row_index)) {
+                draw_cell_at(framebuffer, ox, oy, xo, yo, mini, cell);
+            } else {
+                framebuffer.set_current_color(FOG_COLOR);
+                framebuffer.fill_rect(ox + xo as i32, oy + yo as i32, mini as i32, mini as i32);
+            }
+        }
+    }
+
+    // entidades con icono en el minimapa, solo en celdas vistas
+    for (x, y, color) in world.map_icons() {
+        if !revealed && !fog.is_seen(((x as usize) / block_size, (y as usize) / block_size)) { continue; }
+        let cx = (x / block_size as f32) * mini as f32;
+        let cy = (y / block_size as f32) * mini as f32;
+        framebuffer.set_current_color(color);
+        framebuffer.fill_rect(ox + cx as i32 - 2, oy + cy as i32 - 2, 4, 4);
+    }
+
+    // jugador en el minimapa (posición subcelda para movimiento suave) y hacia dónde mira
+    let pmini_x = ox as f32 + (player.position.x / block_size as f32) * mini as f32;
+    let pmini_y = oy as f32 + (player.position.y / block_size as f32) * mini as f32;
+    framebuffer.set_current_color(Color::BLACK);
+    draw_line(framebuffer, (pmini_x, pmini_y), (pmini_x + 9.0 * player.angle.cos(), pmini_y + 9.0 * player.angle.sin()));
+    framebuffer.fill_rect(pmini_x as i32 - 2, pmini_y as i32 - 2, 5, 5);
+}
+
+/// Minimapa circular centrado en el jugador que gira con él (adelante siempre es arriba).
+/// Muestra `zoom` celdas de radio, el cono de visión y la niebla igual que el fijo.
+pub fn render_minimap_rotating(
+    framebuffer: &mut Framebuffer,
+    (maze, fog, block_size): (&Maze, &Fog, usize),
+    player: &Player,
+    world: &World,
+    (center, radius): ((i32, i32), i32),
+    zoom: f32,
+) {
+    let revealed = world.effects.is_active(EffectKind::MapReveal);
+    let bs = block_size as f32;
+    let world_per_px = zoom * bs / radius.max(1) as f32;
+    // adelante (arriba en el minimapa) y derecha del jugador en el mundo
+    let forward = (player.angle.cos(), player.angle.sin());
+    let right = (-player.angle.sin(), player.angle.cos());
+    let half_fov = player.field_of_view / 2.0;
+    let r = radius as f32;
+
+    for sy in -radius..=radius {
+        for sx in -radius..=radius {
+            let (fx, fy) = (sx as f32 + 0.5, sy as f32 + 0.5);
+            let d = fx.hypot(fy);
+            if d > r { continue; }
+            let (x, y) = (center.0 + sx, center.1 + sy);
+            if x < 0 || y < 0 { continue; }
+            if d > r - 2.0 {
+                framebuffer.set_current_color(BORDER_COLOR);
+                framebuffer.set_pixel(x as u32, y as u32);
+                continue;
+            }
+
+            // píxel del minimapa -> punto del mundo
+            let wx = player.position.x + (fx * right.0 - fy * forward.0) * world_per_px;
+            let wy = player.position.y + (fx * right.1 - fy * forward.1) * world_per_px;
+            let cell = (wx.max(0.0) as usize / block_size, wy.max(0.0) as usize / block_size);
+            let in_maze = wx >= 0.0 && wy >= 0.0 && cell.1 < maze.len() && cell.0 < maze[cell.1].len();
+            let mut color = if !in_maze || (!revealed && !fog.is_seen(cell)) {
+                FOG_COLOR
+            } else if is_walkable(maze[cell.1][cell.0]) && maze[cell.1][cell.0] != 'g' {
+                FLOOR_COLOR
+            } else {
+                cell_to_color(maze[cell.1][cell.0])
+            };
+
+            // cono de visión: se aclara lo que cae dentro del FOV
+            if fx.atan2(-fy).abs() < half_fov {
+                color = lighten(color, 0.25);
+            }
+            framebuffer.set_current_color(color);
+            framebuffer.set_pixel(x as u32, y as u32);
+        }
+    }
+
+    // iconos dentro del círculo, solo en celdas vistas
+    for (x, y, color) in world.map_icons() {
+        if !revealed && !fog.is_seen(((x as usize) / block_size, (y as usize) / block_size)) { continue; }
+        let (dx, dy) = (x - player.position.x, y - player.position.y);
+        let sx = (dx * right.0 + dy * right.1) / world_per_px;
+        let sy = -(dx * forward.0 + dy * forward.1) / world_per_px;
+        if sx.hypot(sy) > r - 4.0 { continue; }
+        framebuffer.set_current_color(color);
+        framebuffer.fill_rect(center.0 + sx as i32 - 2, center.1 + sy as i32 - 2, 4, 4);
+    }
+
+    // jugador: flecha hacia arriba
+    framebuffer.set_current_color(Color::BLACK);
+    let (cx, cy) = (center.0 as f32, center.1 as f32);
+    draw_line(framebuffer, (cx, cy - 7.0), (cx - 4.0, cy + 4.0));
+    draw_line(framebuffer, (cx, cy - 7.0), (cx + 4.0, cy + 4.0));
+    draw_line(framebuffer, (cx - 4.0, cy + 4.0), (cx + 4.0, cy + 4.0));
+    framebuffer.fill_rect(center.0 - 1, center.1 - 1, 3, 3);
+}
+
+// Dibujar una celda en el minimap
+fn draw_cell_at(
+    framebuffer: &mut Framebuffer,
+    ox: i32,
+    oy: i32,
+    xo: usize,
+    yo: usize,
+    block_size: usize,
+    cell: char,
+) {
+    if cell == ' ' { return; }
+    let color = cell_to_color(cell);
+    framebuffer.set_current_color(color);
+    let x0 = ox + xo as i32;
+    let y0 = oy + yo as i32;
+    let pad = 1; // separación de 1px entre celdas, como un padding
+    framebuffer.fill_rect(
+        x0 + pad,
+        y0 + pad,
+        (block_size as i32 - 2 * pad).max(1),
+        (block_size as i32 - 2 * pad).max(1),
+    );
+}
+
+/// Línea de un píxel de ancho con el color actual
+pub fn draw_line(framebuffer: &mut Framebuffer, (x0, y0): (f32, f32), (x1, y1): (f32, f32)) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as i32;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+        if x >= 0.0 && y >= 0.0 {
+            framebuffer.set_pixel(x as u32, y as u32);
+        }
+    }
+}
+
+fn lighten(c: Color, amount: f32) -> Color {
+    let f = |v: u8| (v as f32 + (255.0 - v as f32) * amount) as u8;
+    Color::new(f(c.r), f(c.g), f(c.b), c.a)
+}
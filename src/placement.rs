@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use crate::random::rng;
+use rand::seq::SliceRandom;
+use crate::maze::{Maze, Directives, is_walkable};
+
+/// Marcador de zona candidata: si hay alguna, lo aleatorio se coloca ahí primero
+pub const CANDIDATE_MARKER: char = '?';
+/// Marcador de zona excluida: nunca se coloca nada al azar ahí
+pub const EXCLUDED_MARKER: char = 'x';
+
+/// Reglas para colocar monedas y objetos al azar. Se pueden cambiar por nivel
+/// con directivas en el archivo del laberinto (ej. `@min_coin_spacing 2`).
+pub struct PlacementRules {
+    pub min_spawn_distance: usize, // celdas de camino desde el inicio del jugador
+    pub min_coin_spacing: usize,   // distancia mínima (en celdas, Manhattan) entre monedas
+    pub min_item_spacing: usize,   // igual, entre objetos de poder del mismo tipo
+    pub require_reachable: bool,   // solo celdas a las que se puede llegar desde el inicio
+}
+
+impl PlacementRules {
+    pub fn from_directives(directives: &Directives) -> Self {
+        let mut rules = Self {
+            min_spawn_distance: 2,
+            min_coin_spacing: 1,
+            min_item_spacing: 0,
+            require_reachable: true,
+        };
+        for (key, value) in directives {
+            match key.as_str() {
+                "min_spawn_distance" => rules.min_spawn_distance = value.parse().unwrap_or(rules.min_spawn_distance),
+                "min_coin_spacing" => rules.min_coin_spacing = value.parse().unwrap_or(rules.min_coin_spacing),
+                "min_item_spacing" => rules.min_item_spacing = value.parse().unwrap_or(rules.min_item_spacing),
+                "require_reachable" => rules.require_reachable = value != "false" && value != "0",
+                _ => {}
+            }
+        }
+        rules
+    }
+}
+
+/// Datos del nivel para elegir celdas: zonas candidatas y celdas que cumplen las reglas
+pub struct Placement {
+    pub rules: PlacementRules,
+    free_cells: Vec<(usize, usize)>,
+    candidates: Vec<(usize, usize)>,
+}
+
+impl Placement {
+    /// Lee las zonas marcadas (y las deja como pasillo) y calcula qué celdas sirven
+    pub fn new(maze: &mut Maze, rules: PlacementRules, spawn: (usize, usize)) -> Self {
+        let mut candidates = Vec::new();
+        let mut excluded = Vec::new();
+        for (j, row) in maze.iter_mut().enumerate() {
+            for (i, cell) in row.iter_mut().enumerate() {
+                if *cell == CANDIDATE_MARKER {
+                    candidates.push((i, j));
+                    *cell = ' ';
+                } else if *cell == EXCLUDED_MARKER {
+                    excluded.push((i, j));
+                    *cell = ' ';
+                }
+            }
+        }
+
+        let distances = path_distances(maze, spawn);
+
+        // Celdas libres: caminables, sin el inicio ni la salida, fuera de exclusiones,
+        // alcanzables y a la distancia mínima del inicio
+        let mut free_cells = Vec::new();
+        for (j, row) in maze.iter().enumerate() {
+            for (i, &c) in row.iter().enumerate() {
+                if !is_walkable(c) || c == 'p' || c == 'g' || excluded.contains(&(i, j)) { continue; }
+                match distances[j][i] {
+                    Some(d) if d < rules.min_spawn_distance => continue,
+                    None if rules.require_reachable => continue,
+                    _ => free_cells.push((i, j)),
+                }
+            }
+        }
+        candidates.retain(|cell| free_cells.contains(cell));
+
+        Self { rules, free_cells, candidates }
+    }
+
+    /// Elige hasta `n` celdas al azar que no estén en `occupied` y que queden a `min_spacing`
+    /// de las celdas de `same_kind` y entre sí. Primero usa las zonas candidatas, luego el
+    /// resto del laberinto, y si aun así no alcanza relaja la separación.
+    pub fn choose_cells(
+        &self,
+        n: usize,
+        min_spacing: usize,
+        occupied: &[(usize, usize)],
+        same_kind: &[(usize, usize)],
+    ) -> Vec<(usize, usize)> {
+        let mut random = rng();
+        let mut zone: Vec<(usize, usize)> = self.candidates.clone();
+        let mut rest: Vec<(usize, usize)> = self.free_cells.iter()
+            .filter(|c| !self.candidates.contains(c))
+            .copied()
+            .collect();
+        zone.shuffle(&mut random);
+        rest.shuffle(&mut random);
+        let pool: Vec<(usize, usize)> = zone.into_iter()
+            .chain(rest)
+            .filter(|c| !occupied.contains(c))
+            .collect();
+
+        let mut chosen: Vec<(usize, usize)> = Vec::new();
+        for &cell in &pool {
+            if chosen.len() >= n { break; }
+            let spaced = same_kind.iter().chain(chosen.iter())
+                .all(|&other| manhattan(cell, other) >= min_spacing);
+            if spaced {
+                chosen.push(cell);
+            }
+        }
+
+        if chosen.len() < n {
+            println!("[placement] solo {} de {} celdas cumplen la separación, se relaja la regla", chosen.len(), n);
+            for &cell in &pool {
+                if chosen.len() >= n { break; }
+                if !chosen.contains(&cell) {
+                    chosen.push(cell);
+                }
+            }
+        }
+        chosen
+    }
+}
+
+fn manhattan(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+/// Recorrido en anchura desde `start` por celdas caminables
+fn path_distances(maze: &Maze, start: (usize, usize)) -> Vec<Vec<Option<usize>>> {
+    let mut distances: Vec<Vec<Option<usize>>> = maze.iter().map(|row| vec![None; row.len()]).collect();
+    let mut queue = VecDeque::new();
+    if start.1 < maze.len() && start.0 < maze[start.1].len() {
+        distances[start.1][start.0] = Some(0);
+        queue.push_back(start);
+    }
+
+    while let Some((i, j)) = queue.pop_front() {
+        let d = distances[j][i].unwrap_or(0);
+        let neighbors = [(i + 1, j), (i.wrapping_sub(1), j), (i, j + 1), (i, j.wrapping_sub(1))];
+        for (ni, nj) in neighbors {
+            if nj >= maze.len() || ni >= maze[nj].len() { continue; }
+            if distances[nj][ni].is_some() || !is_walkable(maze[nj][ni]) { continue; }
+            distances[nj][ni] = Some(d + 1);
+            queue.push_back((ni, nj));
+        }
+    }
+    distances
+}
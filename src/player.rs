@@ -1,12 +1,17 @@
 use raylib::prelude::*;
 use std::f32::consts::PI;
 use crate::maze::{Maze, is_walkable};
+use crate::portals::Portals;
 
 pub struct Player {
     pub position: Vector2,  
     pub angle: f32,         
     pub field_of_view: f32, 
     pub collision_radius: f32,
+    pub speed_multiplier: f32, // > 1.0 mientras dura un objeto de velocidad
+    pub mouse_sensitivity: f32, // multiplicador de la sensibilidad base (opciones)
+    pub noclip: bool, // trucos de la consola: atravesar paredes
+    pub god: bool,    // y que no lo atrape la policía ni se le acabe el tiempo
 }
 
 impl Player {
@@ -16,22 +21,58 @@ impl Player {
             angle,
             field_of_view,
             collision_radius: 20.0,
+            speed_multiplier: 1.0,
+            mouse_sensitivity: 1.0,
+            noclip: false,
+            god: false,
         }
     }
 }
 
-// Función auxiliar para verificar colisiones con el radio
-fn check_collision(position: Vector2, radius: f32, maze: &Maze, block_size: usize) -> bool {
-    // Verificar en múltiples puntos alrededor del jugador para una detección más precisa
-    let points = [
+/// Entrada del jugador en un frame; se lee del teclado/mouse o de una repetición grabada
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct InputFrame {
+    pub mouse_turn: f32, // radianes que gira el mouse este frame (ya con la sensibilidad)
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+    pub turn_left: bool,
+    pub turn_right: bool,
+    pub spray: bool, // T apretada este frame (tag de aerosol)
+}
+
+impl InputFrame {
+    pub fn read(raylib_handle: &RaylibHandle, player: &Player) -> Self {
+        const MOUSE_SENSITIVITY: f32 = 0.008;
+        Self {
+            mouse_turn: raylib_handle.get_mouse_delta().x * MOUSE_SENSITIVITY * player.mouse_sensitivity,
+            forward: raylib_handle.is_key_down(KeyboardKey::KEY_W),
+            back: raylib_handle.is_key_down(KeyboardKey::KEY_S),
+            left: raylib_handle.is_key_down(KeyboardKey::KEY_A),
+            right: raylib_handle.is_key_down(KeyboardKey::KEY_D),
+            turn_left: raylib_handle.is_key_down(KeyboardKey::KEY_K),
+            turn_right: raylib_handle.is_key_down(KeyboardKey::KEY_L),
+            spray: raylib_handle.is_key_pressed(KeyboardKey::KEY_T),
+        }
+    }
+}
+
+/// Puntos que se revisan contra las paredes: el centro y los cuatro extremos del radio
+pub fn collision_points(position: Vector2, radius: f32) -> [Vector2; 5] {
+    [
         position,  // Centro
         Vector2 { x: position.x + radius, y: position.y },
         Vector2 { x: position.x - radius, y: position.y },
         Vector2 { x: position.x, y: position.y + radius },
         Vector2 { x: position.x, y: position.y - radius },
-    ];
+    ]
+}
 
-    for point in &points {
+// Función auxiliar para verificar colisiones con el radio
+fn check_collision(position: Vector2, radius: f32, maze: &Maze, block_size: usize) -> bool {
+    // Verificar en múltiples puntos alrededor del jugador para una detección más precisa
+    for point in &collision_points(position, radius) {
         let grid_x = (point.x.max(0.0) as usize) / block_size;
         let grid_y = (point.y.max(0.0) as usize) / block_size;
         
@@ -44,21 +85,53 @@ fn check_collision(position: Vector2, radius: f32, maze: &Maze, block_size: usiz
     false
 }
 
-/// Procesa los eventos de entrada del teclado y mouse para controlar al jugador
-pub fn process_events(player: &mut Player, raylib_handle: &RaylibHandle, maze: &Maze, block_size: usize) {
+/// Si el borde del jugador (hacia donde se mueve) entra en un portal enlazado, devuelve
+/// dónde aparece del otro lado y su nuevo ángulo: ya fuera de la celda destino, a un radio
+/// de distancia. None si no hay portal o si la salida está bloqueada.
+fn through_portal(player: &Player, (dx, dy): (f32, f32), portals: &Portals, maze: &Maze, block_size: usize) -> Option<(Vector2, f32)> {
+    let length = dx.hypot(dy);
+    if length == 0.0 { return None; }
+    let (dir_x, dir_y) = (dx / length, dy / length);
+    let lead_x = player.position.x + dx + dir_x * player.collision_radius;
+    let lead_y = player.position.y + dy + dir_y * player.collision_radius;
+    if lead_x < 0.0 || lead_y < 0.0 { return None; }
+    let cell = (lead_x as usize / block_size, lead_y as usize / block_size);
+    let link = portals.link(cell)?;
+
+    // El rumbo del movimiento gira igual que la vista
+    let heading = dir_y.atan2(dir_x);
+    let ((mut x, mut y), new_heading) = portals.transform(cell, (lead_x, lead_y), heading, block_size)?;
+    let turn = new_heading - heading;
+    let (step_x, step_y) = (new_heading.cos(), new_heading.sin());
+    // Avanzar hasta salir de la celda destino
+    let limit = 2 * block_size;
+    for _ in 0..limit {
+        if x < 0.0 || y < 0.0 || (x as usize / block_size, y as usize / block_size) != link.target { break; }
+        x += step_x;
+        y += step_y;
+    }
+    let exit = Vector2 {
+        x: x + step_x * (player.collision_radius + 1.0),
+        y: y + step_y * (player.collision_radius + 1.0),
+    };
+    if check_collision(exit, player.collision_radius, maze, block_size) { return None; }
+    Some((exit, player.angle + turn))
+}
+
+/// Procesa la entrada del teclado y mouse (ver `InputFrame`) para controlar al jugador.
+/// Atravesar un portal lleva al jugador a la celda enlazada, girado según el portal.
+pub fn process_events(player: &mut Player, input: &InputFrame, maze: &Maze, portals: &Portals, block_size: usize) {
     const MOVEMENT_SPEED: f32 = 10.0;
-    const MOUSE_SENSITIVITY: f32 = 0.008;
     const KEYBOARD_ROTATION_SPEED: f32 = std::f32::consts::PI / 40.0;
     
     // Rotación con mouse
-    let mouse_delta = raylib_handle.get_mouse_delta();
-    player.angle += mouse_delta.x * MOUSE_SENSITIVITY;
+    player.angle += input.mouse_turn;
     
     // Rotación con teclado
-    if raylib_handle.is_key_down(KeyboardKey::KEY_L) { 
+    if input.turn_right {
         player.angle += KEYBOARD_ROTATION_SPEED;
     }
-    if raylib_handle.is_key_down(KeyboardKey::KEY_K) { 
+    if input.turn_left {
         player.angle -= KEYBOARD_ROTATION_SPEED;
     }
 
@@ -68,12 +141,13 @@ pub fn process_events(player: &mut Player, raylib_handle: &RaylibHandle, maze: &
 
     let mut forward_movement = 0.0;
     let mut strafe_movement = 0.0;
+    let movement_speed = MOVEMENT_SPEED * player.speed_multiplier;
 
     // Controles WASD
-    if raylib_handle.is_key_down(KeyboardKey::KEY_W) { forward_movement += MOVEMENT_SPEED; }
-    if raylib_handle.is_key_down(KeyboardKey::KEY_S) { forward_movement -= MOVEMENT_SPEED; }
-    if raylib_handle.is_key_down(KeyboardKey::KEY_D) { strafe_movement -= MOVEMENT_SPEED; }
-    if raylib_handle.is_key_down(KeyboardKey::KEY_A) { strafe_movement += MOVEMENT_SPEED; }
+    if input.forward { forward_movement += movement_speed; }
+    if input.back { forward_movement -= movement_speed; }
+    if input.right { strafe_movement -= movement_speed; }
+    if input.left { strafe_movement += movement_speed; }
 
     if forward_movement != 0.0 || strafe_movement != 0.0 {
         // Vectores de dirección
@@ -88,12 +162,28 @@ pub fn process_events(player: &mut Player, raylib_handle: &RaylibHandle, maze: &
 
         // Normalizar movimiento diagonal
         let movement_magnitude = (movement_x * movement_x + movement_y * movement_y).sqrt();
-        if movement_magnitude > MOVEMENT_SPEED && movement_magnitude > 0.0 {
-            let normalization_factor = MOVEMENT_SPEED / movement_magnitude;
+        if movement_magnitude > movement_speed {
+            let normalization_factor = movement_speed / movement_magnitude;
             movement_x *= normalization_factor;
             movement_y *= normalization_factor;
         }
 
+        // Con noclip se atraviesan paredes y portales, pero no se sale del mapa
+        if player.noclip {
+            let width = maze.first().map_or(0, |row| row.len()) * block_size;
+            let height = maze.len() * block_size;
+            player.position.x = (player.position.x + movement_x).clamp(0.0, (width as f32 - 1.0).max(0.0));
+            player.position.y = (player.position.y + movement_y).clamp(0.0, (height as f32 - 1.0).max(0.0));
+            return;
+        }
+
+        // Portal en el camino: se sale por el otro lado en vez de chocar
+        if let Some((position, angle)) = through_portal(player, (movement_x, movement_y), portals, maze, block_size) {
+            player.position = position;
+            player.angle = (angle + PI).rem_euclid(2.0 * PI) - PI;
+            return;
+        }
+
         // Verificar colisiones en el nuevo movimiento
         let new_position = Vector2 {
             x: player.position.x + movement_x,
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+use crate::maze::{Directives, Maze};
+
+/// Celda espejo: refleja los rayos (y para el jugador es una pared más)
+pub const MIRROR: char = 'M';
+/// Celda portal: lo que entra sale por la celda enlazada con `@portal`
+pub const PORTAL: char = 'O';
+
+/// A qué celda lleva un portal y cuánto gira lo que lo atraviesa (en cuartos de vuelta)
+#[derive(Clone, Copy, Debug)]
+pub struct PortalLink {
+    pub target: (usize, usize),
+    pub quarter_turns: i32,
+}
+
+/// Portales del nivel, enlazados de a pares en ambos sentidos
+pub struct Portals {
+    links: HashMap<(usize, usize), PortalLink>,
+}
+
+impl Portals {
+    pub fn new() -> Self {
+        Self { links: HashMap::new() }
+    }
+
+    /// Lee las directivas `@portal x1 y1 x2 y2 [grados]`; ambas celdas tienen que ser 'O' y el
+    /// giro múltiplo de 90. De vuelta (de la 2 a la 1) se gira al revés.
+    pub fn from_directives(maze: &Maze, directives: &Directives) -> Self {
+        let mut portals = Self::new();
+        for (key, value) in directives {
+            if key != "portal" { continue; }
+            let numbers: Vec<i32> = value.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+            let (a, b, degrees) = match numbers[..] {
+                [x1, y1, x2, y2] => ((x1, y1), (x2, y2), 0),
+                [x1, y1, x2, y2, degrees] => ((x1, y1), (x2, y2), degrees),
+                _ => {
+                    println!("[portals] @portal inválido: {}", value);
+                    continue;
+                }
+            };
+            let is_portal = |(x, y): (i32, i32)| {
+                x >= 0 && y >= 0 && maze.get(y as usize).and_then(|row| row.get(x as usize)) == Some(&PORTAL)
+            };
+            if !is_portal(a) || !is_portal(b) || degrees % 90 != 0 {
+                println!("[portals] @portal {}: las celdas deben ser '{}' y el giro múltiplo de 90", value, PORTAL);
+                continue;
+            }
+            let turns = (degrees / 90).rem_euclid(4);
+            let (a, b) = ((a.0 as usize, a.1 as usize), (b.0 as usize, b.1 as usize));
+            portals.links.insert(a, PortalLink { target: b, quarter_turns: turns });
+            portals.links.insert(b, PortalLink { target: a, quarter_turns: (4 - turns) % 4 });
+        }
+        portals
+    }
+
+    pub fn link(&self, cell: (usize, usize)) -> Option<PortalLink> {
+        self.links.get(&cell).copied()
+    }
+
+    /// Lleva un punto dentro del portal `cell` (y un ángulo) a la celda enlazada: el punto
+    /// queda en la misma posición relativa al centro, girada igual que el ángulo
+    pub fn transform(&self, cell: (usize, usize), (x, y): (f32, f32), angle: f32, block_size: usize) -> Option<((f32, f32), f32)> {
+        let link = self.link(cell)?;
+        let bs = block_size as f32;
+        let center = |(cx, cy): (usize, usize)| ((cx as f32 + 0.5) * bs, (cy as f32 + 0.5) * bs);
+        let (from_x, from_y) = center(cell);
+        let (to_x, to_y) = center(link.target);
+        let rotation = link.quarter_turns as f32 * FRAC_PI_2;
+        let (dx, dy) = (x - from_x, y - from_y);
+        let (sin, cos) = rotation.sin_cos();
+        Some(((to_x + dx * cos - dy * sin, to_y + dx * sin + dy * cos), angle + rotation))
+    }
+}
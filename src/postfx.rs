@@ -0,0 +1,256 @@
+use std::f32::consts::TAU;
+use crate::framebuffer::Framebuffer;
+use crate::settings::Settings;
+
+/// Segundos restantes desde los que empieza el pulso rojo
+const LOW_TIME_SECS: f32 = 10.0;
+/// Distancia (en celdas) desde la que la patrulla sacude la pantalla
+const SHAKE_RANGE: f32 = 4.0;
+/// Desplazamiento máximo del temblor, como fracción del alto del framebuffer
+const SHAKE_AMOUNT: f32 = 0.012;
+/// Lado de la tabla de color ya remuestreada (64³ entradas)
+const LUT_SIZE: usize = 64;
+
+/// Un efecto de la cadena de post-proceso, con sus parámetros
+pub enum Effect {
+    Shake,                    // tiembla con la policía cerca
+    ChromaticAberration(f32), // separación de rojo y azul en los bordes (px a 1.0 de escala)
+    ColorGrade(Lut),          // corrección de color con una tabla
+    LowTimePulse,             // latido rojo en los bordes cuando queda poco tiempo
+    Vignette(f32),            // oscurece las esquinas (0 a 1)
+    Scanlines(f32),           // líneas de CRT (0 a 1)
+}
+
+/// Lo que cambia frame a frame y usan los efectos
+pub struct FrameInfo {
+    pub time_left: f32,               // segundos del nivel
+    pub police_distance: Option<f32>, // patrulla más cercana, en celdas
+}
+
+/// Tabla de color 3D: a cada (r, g, b) le da el color corregido
+pub struct Lut {
+    table: Vec<[u8; 3]>,
+}
+
+impl Lut {
+    /// Carga una tabla en tira PNG (ancho = lado², alto = lado; x = r + b·lado, y = g),
+    /// el formato que exportan la mayoría de los editores
+    pub fn load(path: &str) -> Result<Self, String> {
+        let image = image::open(path).map_err(|e| format!("No se pudo cargar {}: {}", path, e))?.to_rgba8();
+        let size = image.height() as usize;
+        if size < 2 || image.width() as usize != size * size {
+            return Err(format!("{}: se esperaba una tira de {}x{}", path, size * size, size));
+        }
+        let texel = |r: usize, g: usize, b: usize| {
+            let p = image.get_pixel((r + b * size) as u32, g as u32);
+            [p[0] as f32, p[1] as f32, p[2] as f32]
+        };
+        // se remuestrea a LUT_SIZE³ con interpolación trilineal, así luego basta el más cercano
+        Ok(Self::build(|r, g, b| {
+            let scaled = |v: f32| {
+                let v = v * (size - 1) as f32;
+                let i = (v as usize).min(size - 2);
+                (i, v - i as f32)
+            };
+            let ((r0, fr), (g0, fg), (b0, fb)) = (scaled(r), scaled(g), scaled(b));
+            let mut out = [0.0; 3];
+            for (dr, wr) in [(0, 1.0 - fr), (1, fr)] {
+                for (dg, wg) in [(0, 1.0 - fg), (1, fg)] {
+                    for (db, wb) in [(0, 1.0 - fb), (1, fb)] {
+                        let c = texel(r0 + dr, g0 + dg, b0 + db);
+                        for (o, c) in out.iter_mut().zip(c) { *o += c * wr * wg * wb; }
+                    }
+                }
+            }
+            out
+        }))
+    }
+
+    /// Corrección "neón" incluida: sombras hacia el teal, luces hacia el magenta y más saturación
+    pub fn neon() -> Self {
+        Self::build(|r, g, b| {
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+            let saturate = |c: f32| luma + (c - luma) * 1.35;
+            let (shadow, light) = ((1.0 - luma).powi(2), luma * luma);
+            [
+                (saturate(r) - 0.06 * shadow + 0.08 * light) * 255.0,
+                (saturate(g) + 0.04 * shadow - 0.03 * light) * 255.0,
+                (saturate(b) + 0.08 * shadow + 0.06 * light) * 255.0,
+            ]
+        })
+    }
+
+    /// `color` va de 0 a 1 por canal y devuelve de 0 a 255
+    fn build(color: impl Fn(f32, f32, f32) -> [f32; 3]) -> Self {
+        let step = 1.0 / (LUT_SIZE - 1) as f32;
+        let mut table = Vec::with_capacity(LUT_SIZE * LUT_SIZE * LUT_SIZE);
+        for b in 0..LUT_SIZE {
+            for g in 0..LUT_SIZE {
+                for r in 0..LUT_SIZE {
+                    let c = color(r as f32 * step, g as f32 * step, b as f32 * step);
+                    table.push(c.map(|v| v.clamp(0.0, 255.0) as u8));
+                }
+            }
+        }
+        Self { table }
+    }
+
+    fn apply(&self, r: u8, g: u8, b: u8) -> [u8; 3] {
+        let i = |v: u8| (v as usize * (LUT_SIZE - 1) + 127) / 255;
+        self.table[i(r) + i(g) * LUT_SIZE + i(b) * LUT_SIZE * LUT_SIZE]
+    }
+}
+
+/// Etapa de post-proceso: corre su cadena de efectos, en orden, sobre el framebuffer ya
+/// renderizado (antes del HUD, que queda limpio)
+pub struct PostProcess {
+    pub effects: Vec<Effect>,
+    time: f32,
+    scratch: Vec<u8>,
+    radial: Vec<f32>, // distancia al centro de cada píxel (0 centro, 1 esquina), por tamaño
+    radial_size: (u32, u32),
+}
+
+impl PostProcess {
+    /// Arma la cadena según las opciones; los efectos en 0 (o apagados) no se agregan
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut effects = Vec::new();
+        if settings.post_effects {
+            if settings.police_shake { effects.push(Effect::Shake); }
+            if settings.chromatic_aberration > 0.0 { effects.push(Effect::ChromaticAberration(settings.chromatic_aberration)); }
+            match settings.color_lut.as_str() {
+                "" => {}
+                "neon" => effects.push(Effect::ColorGrade(Lut::neon())),
+                path => match Lut::load(path) {
+                    Ok(lut) => effects.push(Effect::ColorGrade(lut)),
+                    Err(e) => println!("[postfx] {}", e),
+                },
+            }
+            if settings.low_time_pulse { effects.push(Effect::LowTimePulse); }
+            if settings.vignette > 0.0 { effects.push(Effect::Vignette(settings.vignette)); }
+            if settings.scanlines > 0.0 { effects.push(Effect::Scanlines(settings.scanlines)); }
+        }
+        Self { effects, time: 0.0, scratch: Vec::new(), radial: Vec::new(), radial_size: (0, 0) }
+    }
+
+    /// Avanza el reloj de los efectos animados (con el tiempo de juego, se congela en pausa)
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, info: &FrameInfo) {
+        if self.effects.is_empty() { return; }
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        if self.radial_size != (width, height) {
+            self.radial = radial_map(width, height);
+            self.radial_size = (width, height);
+        }
+        for effect in &self.effects {
+            let pixels = &mut framebuffer.pixels;
+            match effect {
+                Effect::Shake => {
+                    let Some(distance) = info.police_distance.filter(|d| *d < SHAKE_RANGE) else { continue };
+                    let amount = SHAKE_AMOUNT * height as f32 * (1.0 - distance / SHAKE_RANGE);
+                    let dx = (amount * (self.time * 41.0).sin()) as i32;
+                    let dy = (amount * (self.time * 33.0 + 1.3).sin()) as i32;
+                    shake(pixels, &mut self.scratch, (width, height), (dx, dy));
+                }
+                Effect::ChromaticAberration(strength) => {
+                    let max_shift = strength * width as f32 / 1300.0;
+                    chromatic(pixels, &mut self.scratch, (width, height), max_shift);
+                }
+                Effect::ColorGrade(lut) => {
+                    for pixel in pixels.chunks_exact_mut(4) {
+                        let [r, g, b] = lut.apply(pixel[0], pixel[1], pixel[2]);
+                        pixel[..3].copy_from_slice(&[r, g, b]);
+                    }
+                }
+                Effect::LowTimePulse => {
+                    if info.time_left >= LOW_TIME_SECS { continue; }
+                    // más rápido y fuerte mientras menos queda
+                    let urgency = 1.0 - info.time_left.max(0.0) / LOW_TIME_SECS;
+                    let rate = 1.0 + 2.0 * urgency;
+                    let beat = 0.5 + 0.5 * (self.time * rate * TAU).sin();
+                    let strength = 0.55 * (0.3 + 0.7 * urgency) * beat;
+                    for (pixel, d) in pixels.chunks_exact_mut(4).zip(&self.radial) {
+                        let t = strength * d * d;
+                        pixel[0] = (pixel[0] as f32 + (220.0 - pixel[0] as f32) * t) as u8;
+                        pixel[1] = (pixel[1] as f32 * (1.0 - t)) as u8;
+                        pixel[2] = (pixel[2] as f32 * (1.0 - t)) as u8;
+                    }
+                }
+                Effect::Vignette(strength) => {
+                    for (pixel, d) in pixels.chunks_exact_mut(4).zip(&self.radial) {
+                        let f = 1.0 - strength * smoothstep(0.35, 1.0, *d);
+                        for c in &mut pixel[..3] { *c = (*c as f32 * f) as u8; }
+                    }
+                }
+                Effect::Scanlines(strength) => {
+                    // una línea oscura cada dos, y un leve oscurecimiento de la columna del medio
+                    // de cada trío de píxeles, como la máscara de un CRT
+                    let row_bytes = width as usize * 4;
+                    let dark = 1.0 - strength * 0.5;
+                    let mask = 1.0 - strength * 0.15;
+                    for (y, row) in pixels.chunks_exact_mut(row_bytes).enumerate() {
+                        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                            let f = if y % 2 == 1 { dark } else { 1.0 } * if x % 3 == 1 { mask } else { 1.0 };
+                            for c in &mut pixel[..3] { *c = (*c as f32 * f) as u8; }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn radial_map(width: u32, height: u32) -> Vec<f32> {
+    let (cx, cy) = (width as f32 * 0.5, height as f32 * 0.5);
+    let max = cx.hypot(cy).max(1.0);
+    let mut map = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            map.push((x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy) / max);
+        }
+    }
+    map
+}
+
+/// Corre la imagen (dx, dy) píxeles repitiendo el borde
+fn shake(pixels: &mut [u8], scratch: &mut Vec<u8>, (width, height): (u32, u32), (dx, dy): (i32, i32)) {
+    if dx == 0 && dy == 0 { return; }
+    scratch.clear();
+    scratch.extend_from_slice(pixels);
+    let (w, h) = (width as i32, height as i32);
+    for y in 0..h {
+        let sy = (y - dy).clamp(0, h - 1);
+        for x in 0..w {
+            let sx = (x - dx).clamp(0, w - 1);
+            let (dst, src) = (((y * w + x) * 4) as usize, ((sy * w + sx) * 4) as usize);
+            pixels[dst..dst + 4].copy_from_slice(&scratch[src..src + 4]);
+        }
+    }
+}
+
+/// Toma el rojo un poco hacia afuera y el azul hacia adentro, más fuerte hacia los lados
+fn chromatic(pixels: &mut [u8], scratch: &mut Vec<u8>, (width, height): (u32, u32), max_shift: f32) {
+    scratch.clear();
+    scratch.extend_from_slice(pixels);
+    let w = width as i32;
+    let cx = width as f32 * 0.5;
+    for x in 0..w {
+        let shift = (max_shift * (x as f32 + 0.5 - cx) / cx) as i32;
+        if shift == 0 { continue; }
+        let (red_x, blue_x) = ((x + shift).clamp(0, w - 1), (x - shift).clamp(0, w - 1));
+        for y in 0..height as i32 {
+            let row = y * w;
+            let i = ((row + x) * 4) as usize;
+            pixels[i] = scratch[((row + red_x) * 4) as usize];
+            pixels[i + 2] = scratch[((row + blue_x) * 4 + 2) as usize];
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use raylib::prelude::Color;
+use crate::random;
+use crate::textures::CpuTexture;
+
+/// Lado de las texturas generadas (potencia de 2 para el muestreo rápido)
+pub const PROCEDURAL_SIZE: u32 = 256;
+
+/// Patrones que se pueden generar sin imágenes
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Pattern {
+    Bricks,   // ladrillos trabados con mortero
+    Tiles,    // azulejos cuadrados con junta
+    Concrete, // concreto con ruido y manchas
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "bricks" => Some(Pattern::Bricks),
+            "tiles" => Some(Pattern::Tiles),
+            "concrete" => Some(Pattern::Concrete),
+            _ => None,
+        }
+    }
+
+    /// Colores por defecto: (principal, secundario)
+    pub fn default_colors(self) -> (Color, Color) {
+        match self {
+            Pattern::Bricks => (Color::new(170, 74, 56, 255), Color::new(200, 195, 185, 255)),
+            Pattern::Tiles => (Color::new(225, 230, 235, 255), Color::new(90, 100, 110, 255)),
+            Pattern::Concrete => (Color::new(150, 150, 145, 255), Color::new(105, 105, 102, 255)),
+        }
+    }
+}
+
+/// Genera una textura repetible del patrón; `primary` es el color de la pieza y
+/// `secondary` el de la junta (o de las manchas en el concreto)
+pub fn generate(pattern: Pattern, primary: Color, secondary: Color) -> CpuTexture {
+    let size = PROCEDURAL_SIZE;
+    let mut pixels = vec![0u8; (size * size * 4) as usize];
+    for y in 0..size {
+        for x in 0..size {
+            let c = match pattern {
+                Pattern::Bricks => bricks(x, y, primary, secondary),
+                Pattern::Tiles => tiles(x, y, primary, secondary),
+                Pattern::Concrete => concrete(x, y, primary, secondary),
+            };
+            let i = ((y * size + x) * 4) as usize;
+            pixels[i..i + 4].copy_from_slice(&[c.r, c.g, c.b, 255]);
+        }
+    }
+    CpuTexture::new(size, size, pixels)
+}
+
+fn bricks(x: u32, y: u32, brick: Color, mortar: Color) -> Color {
+    const BRICK_W: u32 = 64;
+    const BRICK_H: u32 = 32;
+    const MORTAR: u32 = 3;
+    let row = y / BRICK_H;
+    let shifted = x + if row % 2 == 1 { BRICK_W / 2 } else { 0 };
+    let column = (shifted / BRICK_W) % (PROCEDURAL_SIZE / BRICK_W);
+    if y % BRICK_H < MORTAR || shifted % BRICK_W < MORTAR {
+        return shade(mortar, 0.9 + 0.1 * grain(x, y));
+    }
+    // cada ladrillo con un tono propio y algo de grano
+    let tone = 0.8 + 0.25 * cell_random(column, row, 1);
+    shade(brick, tone * (0.92 + 0.08 * grain(x, y)))
+}
+
+fn tiles(x: u32, y: u32, tile: Color, grout: Color) -> Color {
+    const TILE: u32 = 32;
+    const GROUT: u32 = 2;
+    let (lx, ly) = (x % TILE, y % TILE);
+    if lx < GROUT || ly < GROUT {
+        return shade(grout, 0.9 + 0.1 * grain(x, y));
+    }
+    // brillo suave arriba a la izquierda de cada azulejo
+    let tone = 0.9 + 0.1 * cell_random(x / TILE, y / TILE, 2);
+    let gloss = 1.0 + 0.12 * (1.0 - (lx + ly) as f32 / (2 * TILE) as f32);
+    shade(tile, tone * gloss)
+}
+
+fn concrete(x: u32, y: u32, base: Color, stain: Color) -> Color {
+    // ruido en varias escalas que se repite en los bordes
+    let n = 0.5 * value_noise(x, y, 8) + 0.3 * value_noise(x, y, 32) + 0.2 * grain(x, y);
+    let stains = (value_noise(x, y, 4) - 0.55).max(0.0) * 2.2;
+    let c = mix(base, stain, stains.min(1.0));
+    shade(c, 0.8 + 0.35 * n)
+}
+
+/// Ruido de valor con `cells` celdas por lado; como la grilla da la vuelta, la textura se repite
+fn value_noise(x: u32, y: u32, cells: u32) -> f32 {
+    let step = PROCEDURAL_SIZE / cells;
+    let (cx, cy) = (x / step, y / step);
+    let tx = smooth((x % step) as f32 / step as f32);
+    let ty = smooth((y % step) as f32 / step as f32);
+    let corner = |dx: u32, dy: u32| cell_random((cx + dx) % cells, (cy + dy) % cells, cells as u64);
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Valor fijo entre 0 y 1 para una celda
+fn cell_random(x: u32, y: u32, salt: u64) -> f32 {
+    (random::mix(((x as u64) << 32) | y as u64 ^ (salt << 56)) & 0xffff) as f32 / 65535.0
+}
+
+fn grain(x: u32, y: u32) -> f32 {
+    cell_random(x, y, 99)
+}
+
+fn shade(c: Color, factor: f32) -> Color {
+    let f = |v: u8| (v as f32 * factor).clamp(0.0, 255.0) as u8;
+    Color::new(f(c.r), f(c.g), f(c.b), 255)
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let f = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t) as u8;
+    Color::new(f(a.r, b.r), f(a.g, b.g), f(a.b, b.b), 255)
+}
@@ -0,0 +1,43 @@
+use std::cell::RefCell;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+// Generador del juego: al azar por defecto, o con semilla fija (--seed / repeticiones)
+thread_local! {
+    static GAME_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_os_rng());
+}
+
+/// Reinicia el generador con una semilla; lo que sigue (colocación, IA) se repite igual
+pub fn seed(seed: u64) {
+    GAME_RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Acceso al generador del juego; se usa igual que `rand::rng()`
+pub fn rng() -> GameRng {
+    GameRng
+}
+
+pub struct GameRng;
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        GAME_RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        GAME_RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        GAME_RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+}
+
+/// Mezclador de bits (SplitMix64): de un número sale otro bien repartido, siempre el mismo.
+/// Sirve para azar estable por posición (graffiti por cara, texturas procedurales).
+pub fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
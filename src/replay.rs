@@ -0,0 +1,144 @@
+use std::fs;
+use crate::player::InputFrame;
+
+/// Versión actual del formato de repeticiones
+pub const REPLAY_VERSION: u32 = 2;
+
+/// Un frame grabado: el dt de la simulación y la entrada del jugador
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub dt: f32,
+    pub input: InputFrame,
+}
+
+/// Partida grabada: nivel, semilla y entrada frame a frame. Con la misma semilla el nivel
+/// se genera igual y la simulación se repite exacta.
+///
+/// Formato de texto: `version=N`, `level=...`, `seed=...` y luego una línea por frame con
+/// `dt giro_mouse teclas`, donde teclas son 7 caracteres 0/1 (W S A D K L y T apretada).
+/// Las de la versión 1 traen 6 (sin T).
+pub struct Replay {
+    pub level: String,
+    pub seed: u64,
+    frames: Vec<ReplayFrame>,
+    cursor: usize,
+}
+
+impl Replay {
+    pub fn new(level: &str, seed: u64) -> Self {
+        Self { level: level.to_string(), seed, frames: Vec::new(), cursor: 0 }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+        let mut replay = Self::new("", 0);
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() { continue; }
+            if let Some((key, value)) = line.split_once('=') {
+                match key {
+                    "version" => {
+                        let version: u32 = value.parse().unwrap_or(0);
+                        if version > REPLAY_VERSION {
+                            println!("[replay] versión {} más nueva que la soportada ({})", version, REPLAY_VERSION);
+                        }
+                    }
+                    "level" => replay.level = value.to_string(),
+                    "seed" => replay.seed = value.parse().map_err(|_| format!("{}:{}: semilla inválida", path, number + 1))?,
+                    _ => {}
+                }
+                continue;
+            }
+            let frame = parse_frame(line).ok_or_else(|| format!("{}:{}: frame inválido", path, number + 1))?;
+            replay.frames.push(frame);
+        }
+        if replay.level.is_empty() {
+            return Err(format!("{}: falta la línea level=", path));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut text = format!("version={}\nlevel={}\nseed={}\n", REPLAY_VERSION, self.level, self.seed);
+        for frame in &self.frames {
+            let input = &frame.input;
+            let keys: String = [input.forward, input.back, input.left, input.right, input.turn_left, input.turn_right, input.spray]
+                .iter()
+                .map(|&down| if down { '1' } else { '0' })
+                .collect();
+            text.push_str(&format!("{} {} {}\n", frame.dt, input.mouse_turn, keys));
+        }
+        fs::write(path, text).map_err(|e| format!("No se pudo escribir {}: {}", path, e))
+    }
+
+    pub fn push(&mut self, frame: ReplayFrame) {
+        self.frames.push(frame);
+    }
+
+    /// Siguiente frame a reproducir; None cuando se terminó la grabación
+    pub fn next_frame(&mut self) -> Option<ReplayFrame> {
+        let frame = self.frames.get(self.cursor).copied();
+        self.cursor += 1;
+        frame
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+fn parse_frame(line: &str) -> Option<ReplayFrame> {
+    let mut parts = line.split_whitespace();
+    let dt = parts.next()?.parse().ok()?;
+    let mouse_turn = parts.next()?.parse().ok()?;
+    let keys: Vec<bool> = parts.next()?.chars().map(|c| c == '1').collect();
+    if keys.len() != 6 && keys.len() != 7 { return None; }
+    Some(ReplayFrame {
+        dt,
+        input: InputFrame {
+            mouse_turn,
+            forward: keys[0],
+            back: keys[1],
+            left: keys[2],
+            right: keys[3],
+            turn_left: keys[4],
+            turn_right: keys[5],
+            spray: keys.get(6).copied().unwrap_or(false),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut replay = Replay::new("maze1.txt", 1234);
+        let input = InputFrame { mouse_turn: -0.25, forward: true, turn_right: true, spray: true, ..InputFrame::default() };
+        replay.push(ReplayFrame { dt: 1.0 / 60.0, input });
+        replay.push(ReplayFrame { dt: 0.02, input: InputFrame::default() });
+
+        let path = std::env::temp_dir().join(format!("replay_test_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        replay.save(path).unwrap();
+        let mut loaded = Replay::load(path).unwrap();
+        let _ = fs::remove_file(path);
+
+        assert_eq!((loaded.level.as_str(), loaded.seed), ("maze1.txt", 1234));
+        assert_eq!(loaded.frame_count(), 2);
+        assert_eq!(loaded.next_frame(), Some(ReplayFrame { dt: 1.0 / 60.0, input }));
+        assert_eq!(loaded.next_frame().map(|f| f.input), Some(InputFrame::default()));
+        assert_eq!(loaded.next_frame(), None);
+    }
+
+    #[test]
+    fn frames_accept_version_1_keys() {
+        let frame = parse_frame("0.016 0.5 100001").unwrap();
+        assert!(frame.input.forward && frame.input.turn_right && !frame.input.spray);
+        assert!(parse_frame("0.016 0.5 1000011").unwrap().input.spray);
+        assert!(parse_frame("0.016 0.5 10").is_none());
+        assert!(parse_frame("x 0.5 100000").is_none());
+        assert!(parse_frame("0.016").is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use crate::storage::KeyValueFile;
+
+/// Versión actual del archivo de récords
+pub const LEADERBOARD_VERSION: u32 = 1;
+const LEADERBOARD_FILE: &str = "leaderboard.cfg";
+/// Entradas que se guardan por nivel
+pub const LEADERBOARD_SIZE: usize = 10;
+
+// las monedas no suman: ganar exige todas las del nivel, así que darían lo mismo siempre
+const POINTS_PER_SECOND: u32 = 100;   // por segundo que sobra en el reloj
+const POINTS_PER_PICKUP: u32 = 150;   // por objeto de poder recogido (menos el de tiempo extra)
+const NO_SIREN_BONUS: u32 = 1000;     // si la policía nunca hizo sonar la sirena
+
+/// Lo que pasó durante una partida, para calcular el puntaje al ganar
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RunStats {
+    pub pickups: usize,
+    pub sirens: usize,
+    pub cheated: bool, // se usó un truco de la consola: no guarda récords ni puntajes
+}
+
+/// Puntaje de una partida ganada, separado por partes para mostrarlo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Score {
+    pub level: usize,
+    pub time_left: u32,
+    pub elapsed: f32,
+    pub time_points: u32,
+    pub pickup_points: u32,
+    pub no_siren_bonus: u32,
+    pub total: u32,
+}
+
+impl Score {
+    pub fn new(level: usize, time_left: u32, elapsed: f32, stats: &RunStats) -> Self {
+        let time_points = time_left * POINTS_PER_SECOND;
+        let pickup_points = stats.pickups as u32 * POINTS_PER_PICKUP;
+        let no_siren_bonus = if stats.sirens == 0 { NO_SIREN_BONUS } else { 0 };
+        Self {
+            level,
+            time_left,
+            elapsed,
+            time_points,
+            pickup_points,
+            no_siren_bonus,
+            total: time_points + pickup_points + no_siren_bonus,
+        }
+    }
+}
+
+/// Una fila de la tabla de récords
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreEntry {
+    pub initials: String,
+    pub score: u32,
+    pub elapsed: f32, // segundos que tardó en ganar; desempata (menos es mejor)
+}
+
+/// Mejores puntajes locales por nivel, ordenados de mayor a menor
+pub struct Leaderboard {
+    levels: HashMap<usize, Vec<ScoreEntry>>,
+}
+
+impl Leaderboard {
+    pub fn new() -> Self {
+        Self { levels: HashMap::new() }
+    }
+
+    /// Carga la tabla; si no existe o no se puede leer, empieza vacía
+    pub fn load() -> Self {
+        let mut board = Self::new();
+        let file = match KeyValueFile::load(LEADERBOARD_FILE) {
+            Ok(Some(file)) => file,
+            Ok(None) => return board,
+            Err(e) => {
+                println!("[leaderboard] {}", e);
+                return board;
+            }
+        };
+        if file.version > LEADERBOARD_VERSION {
+            println!("[leaderboard] archivo de una versión más nueva ({}), se leen solo los datos conocidos", file.version);
+        }
+
+        // versión 1: `level.N.K=INICIALES,puntaje,segundos`
+        for (key, value) in &file.values {
+            let Some(level) = key.strip_prefix("level.")
+                .and_then(|rest| rest.split('.').next())
+                .and_then(|level| level.parse().ok()) else { continue };
+            let parts: Vec<&str> = value.split(',').collect();
+            if let [initials, score, elapsed] = parts[..]
+                && let (Ok(score), Ok(elapsed)) = (score.parse(), elapsed.parse())
+            {
+                board.insert(level, ScoreEntry { initials: sanitize_initials(initials), score, elapsed });
+            }
+        }
+        board
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let mut levels: Vec<&usize> = self.levels.keys().collect();
+        levels.sort();
+        let mut entries = Vec::new();
+        for level in levels {
+            for (rank, entry) in self.levels[level].iter().enumerate() {
+                entries.push((
+                    format!("level.{}.{}", level, rank + 1),
+                    format!("{},{},{:.2}", entry.initials, entry.score, entry.elapsed),
+                ));
+            }
+        }
+        KeyValueFile::save(LEADERBOARD_FILE, LEADERBOARD_VERSION, &entries)
+    }
+
+    pub fn entries(&self, level: usize) -> &[ScoreEntry] {
+        self.levels.get(&level).map_or(&[], |entries| entries.as_slice())
+    }
+
+    /// Posición (desde 0) que tendría un puntaje en la tabla del nivel; None si no entra
+    pub fn rank_for(&self, level: usize, score: u32, elapsed: f32) -> Option<usize> {
+        let entries = self.entries(level);
+        let rank = entries.iter()
+            .position(|e| score > e.score || (score == e.score && elapsed < e.elapsed))
+            .unwrap_or(entries.len());
+        (rank < LEADERBOARD_SIZE).then_some(rank)
+    }
+
+    /// Agrega una entrada en su lugar y recorta la tabla; devuelve su posición si quedó
+    pub fn insert(&mut self, level: usize, entry: ScoreEntry) -> Option<usize> {
+        let rank = self.rank_for(level, entry.score, entry.elapsed)?;
+        let entries = self.levels.entry(level).or_default();
+        entries.insert(rank, entry);
+        entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+}
+
+/// Iniciales en mayúsculas, solo letras y números, máximo 3
+pub fn sanitize_initials(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .take(3)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(initials: &str, score: u32, elapsed: f32) -> ScoreEntry {
+        ScoreEntry { initials: initials.to_string(), score, elapsed }
+    }
+
+    #[test]
+    fn score_adds_up_its_parts() {
+        let score = Score::new(1, 30, 90.0, &RunStats { pickups: 2, sirens: 0, cheated: false });
+        assert_eq!(score.time_points, 30 * POINTS_PER_SECOND);
+        assert_eq!(score.pickup_points, 2 * POINTS_PER_PICKUP);
+        assert_eq!(score.no_siren_bonus, NO_SIREN_BONUS);
+        assert_eq!(score.total, score.time_points + score.pickup_points + score.no_siren_bonus);
+
+        let chased = Score::new(1, 0, 120.0, &RunStats { pickups: 0, sirens: 3, cheated: false });
+        assert_eq!(chased.total, 0);
+    }
+
+    #[test]
+    fn insert_orders_by_score_then_time() {
+        let mut board = Leaderboard::new();
+        assert_eq!(board.insert(1, entry("AAA", 500, 60.0)), Some(0));
+        assert_eq!(board.insert(1, entry("BBB", 900, 80.0)), Some(0));
+        assert_eq!(board.insert(1, entry("CCC", 500, 50.0)), Some(1));
+        assert_eq!(board.insert(1, entry("DDD", 500, 70.0)), Some(3));
+        let initials: Vec<&str> = board.entries(1).iter().map(|e| e.initials.as_str()).collect();
+        assert_eq!(initials, ["BBB", "CCC", "AAA", "DDD"]);
+        assert!(board.entries(2).is_empty());
+    }
+
+    #[test]
+    fn table_keeps_only_the_best() {
+        let mut board = Leaderboard::new();
+        for i in 0..LEADERBOARD_SIZE as u32 {
+            board.insert(1, entry("AAA", 100 + i, 60.0));
+        }
+        assert_eq!(board.rank_for(1, 50, 60.0), None);
+        assert_eq!(board.insert(1, entry("LOW", 50, 60.0)), None);
+        assert_eq!(board.insert(1, entry("TOP", 1000, 60.0)), Some(0));
+        assert_eq!(board.entries(1).len(), LEADERBOARD_SIZE);
+        assert_eq!(board.entries(1).last().unwrap().score, 101);
+    }
+
+    #[test]
+    fn initials_are_sanitized() {
+        assert_eq!(sanitize_initials("a.b-c d"), "ABC");
+        assert_eq!(sanitize_initials("x1"), "X1");
+        assert_eq!(sanitize_initials(""), "");
+    }
+}
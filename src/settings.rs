@@ -0,0 +1,279 @@
+use crate::minimap::{MAX_ZOOM, MIN_ZOOM};
+use std::sync::OnceLock;
+use crate::storage::KeyValueFile;
+
+/// Versión actual del archivo de opciones
+pub const SETTINGS_VERSION: u32 = 1;
+const SETTINGS_FILE: &str = "settings.cfg";
+/// Tope de hilos de render; más que esto no rinde con franjas tan angostas
+const MAX_RENDER_THREADS: usize = 16;
+/// Rango del campo de visión (grados)
+pub const MIN_FOV: f32 = 45.0;
+pub const MAX_FOV: f32 = 110.0;
+
+/// Opciones que el jugador puede cambiar desde el menú de pausa
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub fov_degrees: f32,
+    pub mouse_sensitivity: f32, // multiplicador sobre la sensibilidad base
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub resolution_scale: f32,  // fracción de la ventana que se renderiza (1.0 = completa)
+    pub linear_filter: bool,    // al escalar a la ventana: lineal (suave) o nearest (pixelado)
+    pub show_minimap: bool,
+    pub minimap_rotating: bool, // minimapa circular que gira con el jugador (si no, el mapa entero fijo)
+    pub minimap_zoom: f32,      // celdas de radio del minimapa giratorio
+    pub automap_pauses: bool,   // el mapa completo (Tab) detiene el juego mientras está abierto
+    pub render_threads: usize,  // hilos para renderizar la vista (0 = automático)
+    pub mipmaps: bool,          // texturas reducidas a la distancia (menos parpadeo)
+    pub bilinear_textures: bool, // texturas suavizadas de cerca (si no, nearest)
+    pub post_effects: bool,     // post-proceso de pantalla (apaga todos los efectos de abajo)
+    pub vignette: f32,          // oscurecer esquinas (0 a 1, 0 = apagado)
+    pub scanlines: f32,         // líneas de CRT (0 a 1)
+    pub chromatic_aberration: f32, // separación de color en los bordes, en px (0 a 8)
+    pub color_lut: String,      // tabla de color: "neon", una tira PNG o vacío
+    pub low_time_pulse: bool,   // pulso rojo con poco tiempo
+    pub police_shake: bool,     // temblor con la policía cerca
+    pub window_width: i32,      // tamaño de la ventana al iniciar (el último que tuvo sin pantalla completa)
+    pub window_height: i32,
+    pub fov_override: Option<f32>, // FOV de --fov (no se guarda; el menú lo descarta)
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fov_degrees: 60.0,
+            mouse_sensitivity: 1.0,
+            master_volume: 1.0,
+            music_volume: 0.6,
+            sfx_volume: 1.0,
+            resolution_scale: 1.0,
+            linear_filter: false,
+            show_minimap: true,
+            minimap_rotating: true,
+            minimap_zoom: 5.0,
+            automap_pauses: true,
+            render_threads: 0,
+            mipmaps: true,
+            bilinear_textures: false,
+            post_effects: true,
+            vignette: 0.35,
+            scanlines: 0.0,
+            chromatic_aberration: 0.0,
+            color_lut: String::new(),
+            low_time_pulse: true,
+            police_shake: true,
+            window_width: 1300,
+            window_height: 900,
+            fov_override: None,
+        }
+    }
+}
+
+/// Cada fila de la pantalla de opciones
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SettingsItem {
+    Fov,
+    Sensitivity,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    ResolutionScale,
+    Filter,
+    Minimap,
+    AutomapPauses,
+    RenderThreads,
+    Mipmaps,
+    TextureFilter,
+    PostEffects,
+}
+
+impl SettingsItem {
+    pub const ALL: [SettingsItem; 13] = [
+        SettingsItem::Fov,
+        SettingsItem::Sensitivity,
+        SettingsItem::MasterVolume,
+        SettingsItem::MusicVolume,
+        SettingsItem::SfxVolume,
+        SettingsItem::ResolutionScale,
+        SettingsItem::Filter,
+        SettingsItem::Minimap,
+        SettingsItem::AutomapPauses,
+        SettingsItem::RenderThreads,
+        SettingsItem::Mipmaps,
+        SettingsItem::TextureFilter,
+        SettingsItem::PostEffects,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsItem::Fov => "Campo de vision",
+            SettingsItem::Sensitivity => "Sensibilidad del mouse",
+            SettingsItem::MasterVolume => "Volumen general",
+            SettingsItem::MusicVolume => "Volumen de musica",
+            SettingsItem::SfxVolume => "Volumen de efectos",
+            SettingsItem::ResolutionScale => "Escala de resolucion",
+            SettingsItem::Filter => "Filtro de escalado",
+            SettingsItem::Minimap => "Minimapa",
+            SettingsItem::AutomapPauses => "Pausa en el mapa",
+            SettingsItem::RenderThreads => "Hilos de render",
+            SettingsItem::Mipmaps => "Mipmaps",
+            SettingsItem::TextureFilter => "Filtro de texturas",
+            SettingsItem::PostEffects => "Efectos de pantalla",
+        }
+    }
+}
+
+impl Settings {
+    /// Carga las opciones guardadas; las que falten (o un archivo viejo) quedan por defecto
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let file = match KeyValueFile::load(SETTINGS_FILE) {
+            Ok(Some(file)) => file,
+            Ok(None) => return settings,
+            Err(e) => {
+                println!("[settings] {}", e);
+                return settings;
+            }
+        };
+        if file.version > SETTINGS_VERSION {
+            println!("[settings] archivo de una versión más nueva ({}), se leen solo las opciones conocidas", file.version);
+        }
+
+        let defaults = Self::default();
+        settings.fov_degrees = file.get("fov_degrees").unwrap_or(defaults.fov_degrees).clamp(MIN_FOV, MAX_FOV);
+        settings.mouse_sensitivity = file.get("mouse_sensitivity").unwrap_or(defaults.mouse_sensitivity).clamp(0.25, 3.0);
+        settings.master_volume = file.get("master_volume").unwrap_or(defaults.master_volume).clamp(0.0, 1.0);
+        settings.music_volume = file.get("music_volume").unwrap_or(defaults.music_volume).clamp(0.0, 1.0);
+        settings.sfx_volume = file.get("sfx_volume").unwrap_or(defaults.sfx_volume).clamp(0.0, 1.0);
+        settings.resolution_scale = file.get("resolution_scale").unwrap_or(defaults.resolution_scale).clamp(0.25, 1.0);
+        settings.linear_filter = file.get("linear_filter").unwrap_or(defaults.linear_filter);
+        settings.show_minimap = file.get("show_minimap").unwrap_or(defaults.show_minimap);
+        settings.minimap_rotating = file.get("minimap_rotating").unwrap_or(defaults.minimap_rotating);
+        settings.automap_pauses = file.get("automap_pauses").unwrap_or(defaults.automap_pauses);
+        settings.minimap_zoom = file.get("minimap_zoom").unwrap_or(defaults.minimap_zoom).clamp(MIN_ZOOM, MAX_ZOOM);
+        settings.render_threads = file.get("render_threads").unwrap_or(defaults.render_threads).min(MAX_RENDER_THREADS);
+        settings.mipmaps = file.get("mipmaps").unwrap_or(defaults.mipmaps);
+        settings.bilinear_textures = file.get("bilinear_textures").unwrap_or(defaults.bilinear_textures);
+        settings.post_effects = file.get("post_effects").unwrap_or(defaults.post_effects);
+        settings.vignette = file.get("vignette").unwrap_or(defaults.vignette).clamp(0.0, 1.0);
+        settings.scanlines = file.get("scanlines").unwrap_or(defaults.scanlines).clamp(0.0, 1.0);
+        settings.chromatic_aberration = file.get("chromatic_aberration").unwrap_or(defaults.chromatic_aberration).clamp(0.0, 8.0);
+        settings.color_lut = file.get("color_lut").unwrap_or(defaults.color_lut);
+        settings.low_time_pulse = file.get("low_time_pulse").unwrap_or(defaults.low_time_pulse);
+        settings.police_shake = file.get("police_shake").unwrap_or(defaults.police_shake);
+        settings.window_width = file.get("window_width").unwrap_or(defaults.window_width).max(320);
+        settings.window_height = file.get("window_height").unwrap_or(defaults.window_height).max(240);
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let entries = [
+            ("fov_degrees", self.fov_degrees.to_string()),
+            ("mouse_sensitivity", self.mouse_sensitivity.to_string()),
+            ("master_volume", self.master_volume.to_string()),
+            ("music_volume", self.music_volume.to_string()),
+            ("sfx_volume", self.sfx_volume.to_string()),
+            ("resolution_scale", self.resolution_scale.to_string()),
+            ("linear_filter", self.linear_filter.to_string()),
+            ("show_minimap", self.show_minimap.to_string()),
+            ("minimap_rotating", self.minimap_rotating.to_string()),
+            ("minimap_zoom", self.minimap_zoom.to_string()),
+            ("automap_pauses", self.automap_pauses.to_string()),
+            ("render_threads", self.render_threads.to_string()),
+            ("mipmaps", self.mipmaps.to_string()),
+            ("bilinear_textures", self.bilinear_textures.to_string()),
+            ("post_effects", self.post_effects.to_string()),
+            ("vignette", self.vignette.to_string()),
+            ("scanlines", self.scanlines.to_string()),
+            ("chromatic_aberration", self.chromatic_aberration.to_string()),
+            ("color_lut", self.color_lut.clone()),
+            ("low_time_pulse", self.low_time_pulse.to_string()),
+            ("police_shake", self.police_shake.to_string()),
+            ("window_width", self.window_width.to_string()),
+            ("window_height", self.window_height.to_string()),
+        ];
+        let entries: Vec<(String, String)> = entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        KeyValueFile::save(SETTINGS_FILE, SETTINGS_VERSION, &entries)
+    }
+
+    /// FOV que se usa: el forzado (si hay) o el de las opciones
+    pub fn fov(&self) -> f32 {
+        self.fov_override.unwrap_or(self.fov_degrees)
+    }
+
+    /// Valor de una opción como texto para el menú
+    pub fn value_text(&self, item: SettingsItem) -> String {
+        match item {
+            SettingsItem::Fov => format!("{:.0} grados", self.fov()),
+            SettingsItem::Sensitivity => format!("{:.2}x", self.mouse_sensitivity),
+            SettingsItem::MasterVolume => format!("{:.0}%", self.master_volume * 100.0),
+            SettingsItem::MusicVolume => format!("{:.0}%", self.music_volume * 100.0),
+            SettingsItem::SfxVolume => format!("{:.0}%", self.sfx_volume * 100.0),
+            SettingsItem::ResolutionScale => format!("{:.0}%", self.resolution_scale * 100.0),
+            SettingsItem::Filter => if self.linear_filter { "Lineal".to_string() } else { "Nearest".to_string() },
+            SettingsItem::Minimap => match (self.show_minimap, self.minimap_rotating) {
+                (false, _) => "No".to_string(),
+                (true, false) => "Fijo".to_string(),
+                (true, true) => "Giratorio".to_string(),
+            },
+            SettingsItem::AutomapPauses => if self.automap_pauses { "Si".to_string() } else { "No".to_string() },
+            SettingsItem::RenderThreads => match self.render_threads {
+                0 => format!("Auto ({})", self.render_thread_count()),
+                n => n.to_string(),
+            },
+            SettingsItem::Mipmaps => if self.mipmaps { "Si".to_string() } else { "No".to_string() },
+            SettingsItem::TextureFilter => if self.bilinear_textures { "Bilineal".to_string() } else { "Nearest".to_string() },
+            SettingsItem::PostEffects => if self.post_effects { "Si".to_string() } else { "No".to_string() },
+        }
+    }
+
+    /// Sube (`direction` > 0) o baja una opción un paso, dentro de su rango
+    pub fn adjust(&mut self, item: SettingsItem, direction: i32) {
+        let d = direction.signum() as f32;
+        match item {
+            SettingsItem::Fov => {
+                // se parte del que se ve; desde acá vuelve a mandar el de las opciones
+                self.fov_degrees = (self.fov().clamp(MIN_FOV, MAX_FOV) + 5.0 * d).clamp(MIN_FOV, MAX_FOV);
+                self.fov_override = None;
+            }
+            SettingsItem::Sensitivity => self.mouse_sensitivity = (self.mouse_sensitivity + 0.25 * d).clamp(0.25, 3.0),
+            SettingsItem::MasterVolume => self.master_volume = step_volume(self.master_volume, d),
+            SettingsItem::MusicVolume => self.music_volume = step_volume(self.music_volume, d),
+            SettingsItem::SfxVolume => self.sfx_volume = step_volume(self.sfx_volume, d),
+            SettingsItem::ResolutionScale => self.resolution_scale = (self.resolution_scale + 0.25 * d).clamp(0.25, 1.0),
+            SettingsItem::Filter => self.linear_filter = !self.linear_filter,
+            SettingsItem::Minimap => {
+                // No -> Fijo -> Giratorio (al revés con la otra dirección)
+                let modes = [(false, self.minimap_rotating), (true, false), (true, true)];
+                let current = modes.iter().position(|&m| m == (self.show_minimap, self.minimap_rotating)).unwrap_or(0);
+                (self.show_minimap, self.minimap_rotating) = modes[(current as i32 + direction.signum()).rem_euclid(3) as usize];
+            }
+            SettingsItem::AutomapPauses => self.automap_pauses = !self.automap_pauses,
+            SettingsItem::RenderThreads => {
+                self.render_threads = self.render_threads.saturating_add_signed(direction.signum() as isize).min(MAX_RENDER_THREADS);
+            }
+            SettingsItem::Mipmaps => self.mipmaps = !self.mipmaps,
+            SettingsItem::TextureFilter => self.bilinear_textures = !self.bilinear_textures,
+            SettingsItem::PostEffects => self.post_effects = !self.post_effects,
+        }
+    }
+
+    /// Hilos que se usan al renderizar: el valor elegido o, en automático, los núcleos disponibles
+    pub fn render_thread_count(&self) -> usize {
+        match self.render_threads {
+            0 => {
+                // se consulta una sola vez: se pide en cada frame y el sistema no cambia
+                static CORES: OnceLock<usize> = OnceLock::new();
+                *CORES.get_or_init(|| std::thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_RENDER_THREADS))
+            }
+            n => n,
+        }
+    }
+}
+
+// Pasos de 10% sin arrastrar error de punto flotante
+fn step_volume(volume: f32, d: f32) -> f32 {
+    ((volume * 10.0).round() + d).clamp(0.0, 10.0) / 10.0
+}
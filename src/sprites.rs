@@ -1,34 +1,163 @@
 use std::f32::consts::PI;
+use std::path::Path;
 use raylib::prelude::*;
 use std::collections::HashMap;
-use rand::seq::SliceRandom;
-use rand::rng;
-use image::ImageReader;
-use crate::maze::{Maze, is_walkable};
 use crate::player::Player;
-use crate::framebuffer::Framebuffer;
-
-struct CpuImage {
-    width: u32,
-    height: u32,
-    pixel_data: Vec<u8>, 
-}
-
-fn normalize_angle(mut angle_radians: f32) -> f32 {
+use crate::framebuffer::Stripe;
+use crate::textures::{CpuTexture, TextureId, TextureStore};
+use crate::effects::{EffectKind, TIME_BONUS_COLOR};
+
+/// Los sprites se dibujan si caen dentro del FOV agrandado por este factor (los anchos
+/// asoman por el borde aunque su centro esté afuera)
+pub const FOV_CULL_MARGIN: f32 = 1.05;
+/// Color de transparencia de las hojas viejas (magenta); se vuelve alpha 0 al cargarlas
+const COLOR_KEY: Color = Color::new(152, 0, 136, 255);
+/// Bajo este alpha el texel filtrado no se pinta (los bordes bilineales quedan a la mitad)
+const ALPHA_CUTOFF: u8 = 128;
+
+pub fn normalize_angle(mut angle_radians: f32) -> f32 {
     while angle_radians >  PI { angle_radians -= 2.0 * PI; }
     while angle_radians < -PI { angle_radians += 2.0 * PI; }
     angle_radians
 }
 
-fn load_any_image_rgba8(path: &str) -> Result<CpuImage, String> {
-    let reader = ImageReader::open(path)
-        .map_err(|e| format!("open {}: {}", path, e))?
-        .with_guessed_format()
-        .map_err(|e| format!("format {}: {}", path, e))?;
-    let dynimg = reader.decode().map_err(|e| format!("decode {}: {}", path, e))?;
-    let rgba = dynimg.to_rgba8();
-    let (w, h) = rgba.dimensions();
-    Ok(CpuImage { width: w, height: h, pixel_data: rgba.into_raw() })
+/// Genera una hoja de `frames` orbes que laten, para objetos que todavía no tienen arte
+fn generate_orb_sheet(size: u32, frames: u32, color: Color) -> CpuTexture {
+    let width = size * frames;
+    let mut pixel_data = vec![0u8; (width * size * 4) as usize];
+    let center = size as f32 * 0.5;
+    for frame in 0..frames {
+        let phase = frame as f32 / frames as f32 * 2.0 * PI;
+        let radius = center * (0.8 + 0.15 * phase.sin());
+        for y in 0..size {
+            for x in 0..size {
+                let d = (x as f32 + 0.5 - center).hypot(y as f32 + 0.5 - center) / radius;
+                if d > 1.0 { continue; }
+                // Centro casi blanco que se va al color del efecto hacia el borde
+                let t = d * d;
+                let i = ((y * width + frame * size + x) * 4) as usize;
+                pixel_data[i] = (255.0 * (1.0 - t) + color.r as f32 * t) as u8;
+                pixel_data[i + 1] = (255.0 * (1.0 - t) + color.g as f32 * t) as u8;
+                pixel_data[i + 2] = (255.0 * (1.0 - t) + color.b as f32 * t) as u8;
+                pixel_data[i + 3] = 255;
+            }
+        }
+    }
+    CpuTexture::new(width, size, pixel_data)
+}
+
+/// Corta la hoja en una textura por frame, con el color-key ya transparente: así el
+/// bilineal y los mipmaps de cada frame no toman texels de los frames vecinos
+fn split_frames(sheet: &CpuTexture, columns: u32, rows: u32) -> Vec<CpuTexture> {
+    let (width, height) = ((sheet.width / columns).max(1), (sheet.height / rows).max(1));
+    (0..columns * rows).map(|frame| {
+        let (x0, y0) = (frame % columns * width, frame / columns * height);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let c = sheet.texel(x0 + x, y0 + y);
+                let alpha = if c == COLOR_KEY { 0 } else { c.a };
+                pixels.extend_from_slice(&[c.r, c.g, c.b, alpha]);
+            }
+        }
+        CpuTexture::new(width, height, pixels)
+    }).collect()
+}
+
+/// Forma de repetir una animación al llegar a su último frame
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoopMode {
+    Loop,     // vuelve al primer frame
+    Once,     // se queda en el último frame
+    PingPong, // recorre los frames de ida y vuelta
+}
+
+/// Animación con nombre dentro de una hoja de sprites
+pub struct Animation {
+    pub frames: Vec<u32>,    // Índices de frame dentro de la cuadrícula (fila por fila)
+    pub frame_duration: f32, // Segundos que dura cada frame
+    pub mode: LoopMode,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<u32>, frame_duration: f32, mode: LoopMode) -> Self {
+        Self { frames, frame_duration, mode }
+    }
+
+    /// Frame de la hoja que toca mostrar tras `elapsed` segundos de animación
+    pub fn frame_at(&self, elapsed: f32) -> u32 {
+        let n = self.frames.len();
+        if n == 0 { return 0; }
+        if n == 1 || self.frame_duration <= 0.0 { return self.frames[0]; }
+
+        let step = (elapsed.max(0.0) / self.frame_duration) as usize;
+        let index = match self.mode {
+            LoopMode::Loop => step % n,
+            LoopMode::Once => step.min(n - 1),
+            LoopMode::PingPong => {
+                let period = 2 * n - 2;
+                let i = step % period;
+                if i < n { i } else { period - i }
+            }
+        };
+        self.frames[index]
+    }
+}
+
+/// Estado de animación propio de cada sprite, avanzado con el tiempo de juego
+#[derive(Clone, Default)]
+pub struct AnimationState {
+    pub name: String, // Animación actual; vacío = la animación por defecto de la hoja
+    pub elapsed: f32, // Segundos transcurridos desde que empezó la animación
+}
+
+impl AnimationState {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), elapsed: 0.0 }
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+}
+
+/// Imagen dividida en una cuadrícula de frames, con sus animaciones.
+/// Si `directions` es 4 u 8, cada fila es un ángulo de vista (fila 0 = de frente,
+/// luego en sentido de ángulo creciente) y las columnas son los frames de animación.
+struct SpriteSheet {
+    frames: Vec<TextureId>, // un frame por textura del store compartido, fila por fila
+    columns: u32,
+    rows: u32,
+    directions: u32,
+    animations: HashMap<String, Animation>,
+    default_animation: String,
+}
+
+impl SpriteSheet {
+    fn frame_count(&self) -> u32 {
+        (self.columns * self.rows).max(1)
+    }
+
+    /// Dirección (fila) a mostrar según hacia dónde mira el sprite y desde dónde se le ve
+    fn direction_for(&self, facing: f32, sprite_x: f32, sprite_y: f32, viewer_x: f32, viewer_y: f32) -> u32 {
+        if self.directions <= 1 { return 0; }
+        let to_viewer = (viewer_y - sprite_y).atan2(viewer_x - sprite_x);
+        let relative = (to_viewer - facing).rem_euclid(2.0 * PI);
+        let sector = 2.0 * PI / self.directions as f32;
+        ((relative / sector).round() as u32) % self.directions
+    }
+
+    fn frame_for(&self, state: &AnimationState, direction: u32) -> u32 {
+        let anim = self.animations.get(&state.name)
+            .or_else(|| self.animations.get(&self.default_animation));
+        let frame = anim.map_or(0, |a| a.frame_at(state.elapsed));
+        if self.directions > 1 {
+            direction.min(self.rows - 1) * self.columns + frame % self.columns
+        } else {
+            // Si la hoja tiene menos frames (imagen estática de respaldo) se da la vuelta
+            frame % self.frame_count()
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -38,105 +167,129 @@ pub struct Sprite {
     pub size: f32, // Tamaño en píxeles del mundo (antes de proyección)
     pub sprite_type: char, // Tipo de sprite: 'c' = moneda, 'P' = policía, 'Q' = portal
     pub is_active: bool, // Si el sprite está activo/visible
+    pub animation: AnimationState, // Animación actual del sprite
+    pub facing: f32, // Ángulo (radianes) hacia el que mira, para sprites con varias direcciones
 }
 
 pub struct SpriteManager {
-    sprite_textures: HashMap<char, CpuImage>, // Texturas de sprites cargadas
+    sprite_sheets: HashMap<char, SpriteSheet>, // Hojas de sprites cargadas
 }
 
 impl SpriteManager {
-    pub fn new() -> Result<Self, String> {
-        let mut sprite_textures = HashMap::new();
+    /// Decodifica las hojas una sola vez dentro del store compartido con las paredes
+    pub fn new(store: &mut TextureStore) -> Result<Self, String> {
+        let mut sprite_sheets = HashMap::new();
+
+        // (tipo, hoja animada, imagen estática de respaldo, columnas, filas, direcciones)
+        // Mientras la hoja no exista en assets se usa la imagen estática como hoja de 1x1
+        let defs: Vec<(char, &str, &str, u32, u32, u32)> = vec![
+            ('c', "assets/coin_sheet.png", "assets/coin.webp", 8, 1, 1),
+            ('P', "assets/police_sheet.png", "assets/police.png", 2, 8, 8),
+            ('Q', "assets/keys_sheet.png", "assets/keys.webp", 4, 1, 1),
+        ];
+
+        for (ch, sheet_path, fallback_path, columns, rows, directions) in defs {
+            let (image, columns, rows, directions) = if Path::new(sheet_path).exists() {
+                (CpuTexture::load(sheet_path)?, columns, rows, directions)
+            } else {
+                (CpuTexture::load(fallback_path)?, 1, 1, 1)
+            };
+            sprite_sheets.insert(ch, SpriteSheet {
+                frames: split_frames(&image, columns, rows).into_iter().map(|frame| store.add(frame)).collect(),
+                columns,
+                rows,
+                directions,
+                animations: HashMap::new(),
+                default_animation: String::new(),
+            });
+        }
 
-        // Cargar sprites
-        let coin = load_any_image_rgba8("assets/coin.webp")?;
-        let police = load_any_image_rgba8("assets/police.png")?;
-        let keys = load_any_image_rgba8("assets/keys.webp")?;
+        // Objetos de poder: si la hoja no existe se genera un orbe que late del color del efecto
+        let orbs = [
+            ('t', "assets/time_sheet.png", TIME_BONUS_COLOR),
+            ('s', "assets/speed_sheet.png", EffectKind::SpeedBoost.color()),
+            ('m', "assets/map_sheet.png", EffectKind::MapReveal.color()),
+            ('f', "assets/freeze_sheet.png", EffectKind::PoliceFreeze.color()),
+        ];
+        for (ch, sheet_path, color) in orbs {
+            let image = if Path::new(sheet_path).exists() {
+                CpuTexture::load(sheet_path)?
+            } else {
+                generate_orb_sheet(64, 6, color)
+            };
+            sprite_sheets.insert(ch, SpriteSheet {
+                frames: split_frames(&image, 6, 1).into_iter().map(|frame| store.add(frame)).collect(),
+                columns: 6,
+                rows: 1,
+                directions: 1,
+                animations: HashMap::new(),
+                default_animation: String::new(),
+            });
+        }
 
-        sprite_textures.insert('c', coin);
-        sprite_textures.insert('P', police);
-        sprite_textures.insert('Q', keys);
+        let mut manager = Self { sprite_sheets };
 
-        Ok(Self { sprite_textures })
-    }
-
-    pub fn get_size(&self, sprite_type: char) -> (u32, u32) {
-        if let Some(img) = self.sprite_textures.get(&sprite_type) {
-            (img.width, img.height)
-        } else {
-            (1, 1)
+        // Moneda girando, luz de la patrulla parpadeando y llave que aparece al completar
+        manager.add_animation('c', "spin", Animation::new((0..8).collect(), 0.08, LoopMode::Loop));
+        manager.add_animation('P', "flash", Animation::new(vec![0, 1], 0.25, LoopMode::PingPong));
+        manager.add_animation('Q', "appear", Animation::new((0..4).collect(), 0.12, LoopMode::Once));
+        for ch in ['t', 's', 'm', 'f'] {
+            manager.add_animation(ch, "pulse", Animation::new((0..6).collect(), 0.1, LoopMode::Loop));
         }
+
+        Ok(manager)
     }
 
-    pub fn sample(&self, sprite_type: char, texture_x: u32, texture_y: u32) -> Color {
-        if let Some(img) = self.sprite_textures.get(&sprite_type) {
-            let width = img.width.max(1);
-            let height = img.height.max(1);
-            let x = texture_x.min(width - 1);
-            let y = texture_y.min(height - 1);
-            let pixel_index = ((y * width + x) as usize) * 4;
-            if pixel_index + 3 < img.pixel_data.len() {
-                let r = img.pixel_data[pixel_index];
-                let g = img.pixel_data[pixel_index + 1];
-                let b = img.pixel_data[pixel_index + 2];
-                let a = img.pixel_data[pixel_index + 3];
-                return Color::new(r, g, b, a);
+    /// Registra una animación para un tipo de sprite; la primera queda como animación por defecto
+    pub fn add_animation(&mut self, sprite_type: char, name: &str, animation: Animation) {
+        if let Some(sheet) = self.sprite_sheets.get_mut(&sprite_type) {
+            if sheet.default_animation.is_empty() {
+                sheet.default_animation = name.to_string();
             }
+            sheet.animations.insert(name.to_string(), animation);
         }
-        Color::WHITE
     }
-}
 
-/// Selecciona `n` celdas libres al azar (no son paredes) y crea sprites “moneda”
-pub fn spawn_coins(maze: &Maze, block_size: usize, n: usize) -> Vec<Sprite> {
-    let mut free_cells = Vec::new();
-    for (j, row) in maze.iter().enumerate() {
-        for (i, &c) in row.iter().enumerate() {
-            if is_walkable(c) {
-                free_cells.push((i, j));
+    /// Frame de la hoja que corresponde a la animación del sprite y al ángulo desde el que se le ve
+    pub fn frame_index(&self, sprite: &Sprite, viewer_x: f32, viewer_y: f32) -> u32 {
+        self.sprite_sheets.get(&sprite.sprite_type).map_or(0, |sheet| {
+            let direction = sheet.direction_for(sprite.facing, sprite.world_x, sprite.world_y, viewer_x, viewer_y);
+            sheet.frame_for(&sprite.animation, direction)
+        })
+    }
+
+    /// Alto en texels de un frame del tipo de sprite (para elegir el mipmap)
+    pub fn frame_height(&self, store: &TextureStore, sprite_type: char) -> u32 {
+        self.sprite_sheets.get(&sprite_type)
+            .and_then(|sheet| sheet.frames.first())
+            .map_or(1, |&id| store.get(id).height)
+    }
+
+    /// Muestrea el color en (u, v) de 0 a 1 dentro del frame indicado, con el filtrado de las
+    /// opciones. Cada frame es su propia textura (con sus mipmaps), y se muestrea sin repetir.
+    pub fn sample(&self, store: &TextureStore, sprite_type: char, frame: u32, (u, v): (f32, f32), lod: f32) -> Color {
+        match self.sprite_sheets.get(&sprite_type) {
+            Some(sheet) => {
+                let id = sheet.frames[frame as usize % sheet.frames.len()];
+                store.get(id).sample_clamped(u, v, lod, store.sampling())
             }
+            None => Color::WHITE,
         }
     }
-    free_cells.shuffle(&mut rng());
-
-    let mut out = Vec::new();
-    for (i, j) in free_cells.into_iter().take(n) {
-        let x = (i * block_size + block_size / 2) as f32;
-        let y = (j * block_size + block_size / 2) as f32;
-        out.push(Sprite {
-            world_x: x,
-            world_y: y,
-            size: (block_size as f32) * 0.4, // moneda más chica (40% de la celda)
-            sprite_type: 'c',
-            is_active: true,
-        });
-    }
-    out
 }
 
-
+/// Dibuja los sprites en una franja de columnas; `depth_buffer` es el de esa franja
 pub fn render_sprites(
-    framebuffer: &mut Framebuffer,
+    stripe: &mut Stripe,
     player: &Player,
-    sprites: &mut [Sprite],
-    portal_position: (f32, f32),
-    coins_collected: usize,
-    coins_needed: usize,
-    sprite_manager: &mut SpriteManager,
+    sprites: &[Sprite],
+    sprite_manager: &SpriteManager,
+    store: &TextureStore,
     block_size: usize,
-    projection_distance: f32,
     depth_buffer: &[f32],
 ) {
-    // Construir lista: monedas vivas + sprite de cierre nivel (police/keys)
-    let mut visible_sprites = Vec::new();
-    visible_sprites.extend(sprites.iter().filter(|s| s.is_active).cloned());
-    visible_sprites.push(Sprite {
-        world_x: portal_position.0,
-        world_y: portal_position.1,
-        size: block_size as f32 * 1.0,
-        sprite_type: if coins_collected >= coins_needed { 'Q' } else { 'P' },
-        is_active: true,
-    });
+    // Construir lista de sprites vivos
+    let mut visible_sprites: Vec<&Sprite> = sprites.iter().filter(|s| s.is_active).collect();
 
     // Ordenar de lejos a cerca 
     visible_sprites.sort_by(|a, b| {
@@ -145,8 +298,9 @@ pub fn render_sprites(
         distance_b.partial_cmp(&distance_a).unwrap_or(std::cmp::Ordering::Equal)
     });
 
-    let half_width = framebuffer.width as f32 * 0.5;
-    let half_height = framebuffer.height as f32 * 0.5;
+    let half_width = stripe.frame_width as f32 * 0.5;
+    let half_height = stripe.height as f32 * 0.5;
+    let projection_distance = half_width / (player.field_of_view * 0.5).tan();
 
     for sprite in visible_sprites {
         // Vector del jugador al sprite
@@ -158,7 +312,7 @@ pub fn render_sprites(
 
         // Culling por FOV
         let fov_half = player.field_of_view * 0.5;
-        if diff.abs() > fov_half * 1.05 { continue; }
+        if diff.abs() > fov_half * FOV_CULL_MARGIN { continue; }
 
         let distance = dx.hypot(dy);
         let mut perp = (distance * diff.cos().abs()).max(0.0001);
@@ -174,60 +328,33 @@ pub fn render_sprites(
         let y0 = (half_height - sprite_height * 0.5).floor() as i32;
         let y1 = (half_height + sprite_height * 0.5).ceil()  as i32;
 
-        // Tamaño de textura
-        let (texture_width, texture_height) = sprite_manager.get_size(sprite.sprite_type);
-        let texture_width = texture_width as i32; let texture_height = texture_height as i32;
-        if texture_width <= 0 || texture_height <= 0 { continue; }
+        // Frame de la hoja según la animación y el ángulo
+        let frame = sprite_manager.frame_index(sprite, player.position.x, player.position.y);
+        // Nivel de mipmap: cuántos texels del frame caen en cada píxel del sprite
+        let lod = (sprite_manager.frame_height(store, sprite.sprite_type) as f32 / sprite_height.max(1.0)).log2();
 
-        // Raster columna a columna
-        for sx in x0.max(0) ..= x1.min(framebuffer.width as i32 - 1) {
-            let col = sx as usize;
+        // Raster columna a columna, solo las que caen en esta franja
+        for sx in x0.max(stripe.x0 as i32) ..= x1.min((stripe.x0 + stripe.width) as i32 - 1) {
+            let col = (sx as u32 - stripe.x0) as usize;
 
             // Oclusión: si pared está delante de este sprite en esta columna, saltar
             if col < depth_buffer.len() && perp >= depth_buffer[col] - 0.001 { continue; }
 
             let u = (sx as f32 - (screen_x - sprite_width * 0.5)) / sprite_width; // [0,1]
             if !(0.0..=1.0).contains(&u) { continue; }
-            let texture_x = (u * texture_width as f32).clamp(0.0, (texture_width - 1) as f32) as i32;
 
             let yy0 = y0.max(0);
-            let yy1 = y1.min(framebuffer.height as i32 - 1);
+            let yy1 = y1.min(stripe.height as i32 - 1);
             for sy in yy0 ..= yy1 {
                 let v = (sy as f32 - y0 as f32) / ((y1 - y0).max(1) as f32);
                 if !(0.0..=1.0).contains(&v) { continue; }
-                let texture_y = (v * texture_height as f32).clamp(0.0, (texture_height - 1) as f32) as i32;
 
-                let c = sprite_manager.sample(sprite.sprite_type, texture_x as u32, texture_y as u32);
-                // Transparencia: alpha o color-key opcional
-                if c.a < 16 { continue; }
-                if c.r == 152 && c.g == 0 && c.b == 136 && c.a == 255 { continue; }
+                let c = sprite_manager.sample(store, sprite.sprite_type, frame, (u, v), lod);
+                // Transparencia (el color-key ya es alpha 0 desde la carga)
+                if c.a < ALPHA_CUTOFF { continue; }
 
-                framebuffer.set_current_color(c);
-                framebuffer.set_pixel(sx as u32, sy as u32);
+                stripe.set_pixel(sx as u32, sy as u32, c);
             }
         }
     }
 }
-
-/// Verifica y recoge monedas cercanas al jugador
-pub fn pickup_coins(player: &Player, sprites: &mut [Sprite], block_size: usize) -> usize {
-    let pickup_radius = 0.35 * block_size as f32;
-
-    for sprite in sprites.iter_mut() {
-        if sprite.is_active && sprite.sprite_type == 'c' {
-            let distance_to_player = (sprite.world_x - player.position.x)
-                .hypot(sprite.world_y - player.position.y);
-            if distance_to_player < pickup_radius { 
-                sprite.is_active = false; 
-            }
-        }
-    }
-
-    // Conteo de monedas
-    let total_coins = sprites.iter().filter(|s| s.sprite_type == 'c').count();
-    let remaining_coins = sprites.iter()
-        .filter(|s| s.sprite_type == 'c' && s.is_active)
-        .count();
-    
-    total_coins.saturating_sub(remaining_coins)
-}
\ No newline at end of file
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Carpeta del juego dentro del directorio de configuración del usuario
+const APP_DIR: &str = "subway_surfer";
+/// Versión actual del archivo de guardado
+pub const SAVE_VERSION: u32 = 1;
+
+/// Directorio de configuración del usuario según el sistema
+/// (XDG_CONFIG_HOME o ~/.config, %APPDATA% en Windows, ~/Library/Application Support en macOS)
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|dir| dir.join(APP_DIR))
+}
+
+/// Ruta de un archivo del juego en el directorio de configuración
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name))
+}
+
+/// Archivo `clave=valor` con versión en la primera línea (`version=N`). Las líneas vacías
+/// y las que empiezan con '#' se ignoran; un archivo sin versión se toma como versión 0.
+pub struct KeyValueFile {
+    pub version: u32,
+    pub values: HashMap<String, String>,
+}
+
+impl KeyValueFile {
+    pub fn new() -> Self {
+        Self { version: 0, values: HashMap::new() }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut file = Self::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let (key, value) = (key.trim(), value.trim());
+            if key == "version" {
+                file.version = value.parse().unwrap_or(0);
+            } else {
+                file.values.insert(key.to_string(), value.to_string());
+            }
+        }
+        file
+    }
+
+    /// Lee el archivo; Ok(None) si todavía no existe
+    pub fn load(name: &str) -> Result<Option<Self>, String> {
+        let path = config_file(name).ok_or("No se encontró el directorio de configuración")?;
+        if !path.exists() { return Ok(None); }
+        let text = fs::read_to_string(&path).map_err(|e| format!("No se pudo leer {}: {}", path.display(), e))?;
+        Ok(Some(Self::parse(&text)))
+    }
+
+    /// Escribe las entradas en orden, con la versión dada al inicio
+    pub fn save(name: &str, version: u32, entries: &[(String, String)]) -> Result<(), String> {
+        let dir = config_dir().ok_or("No se encontró el directorio de configuración")?;
+        fs::create_dir_all(&dir).map_err(|e| format!("No se pudo crear {}: {}", dir.display(), e))?;
+        let path = dir.join(name);
+        let mut text = format!("version={}\n", version);
+        for (key, value) in entries {
+            text.push_str(&format!("{}={}\n", key, value));
+        }
+        fs::write(&path, text).map_err(|e| format!("No se pudo escribir {}: {}", path.display(), e))
+    }
+
+    /// Valor de una clave convertido al tipo pedido; None si falta o no se puede leer
+    pub fn get<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.values.get(key).and_then(|value| value.parse().ok())
+    }
+}
+
+/// Progreso del jugador: niveles desbloqueados y mejores tiempos (segundos) por nivel
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveData {
+    pub unlocked_levels: usize,
+    pub best_times: HashMap<usize, f32>,
+}
+
+impl SaveData {
+    const FILE: &'static str = "save.cfg";
+
+    pub fn new() -> Self {
+        Self { unlocked_levels: 1, best_times: HashMap::new() }
+    }
+
+    /// Carga el progreso; si no existe o no se puede leer, empieza de cero
+    pub fn load() -> Self {
+        let file = match KeyValueFile::load(Self::FILE) {
+            Ok(Some(file)) => file,
+            Ok(None) => return Self::new(),
+            Err(e) => {
+                println!("[save] {}", e);
+                return Self::new();
+            }
+        };
+        if file.version > SAVE_VERSION {
+            println!("[save] archivo de una versión más nueva ({}), se leen solo los datos conocidos", file.version);
+        }
+
+        let mut data = Self::new();
+        data.unlocked_levels = file.get("unlocked_levels").unwrap_or(1).max(1);
+        // versión 1: `best_time.N=segundos` por nivel
+        for (key, value) in &file.values {
+            if let Some(level) = key.strip_prefix("best_time.").and_then(|l| l.parse().ok())
+                && let Ok(seconds) = value.parse()
+            {
+                data.best_times.insert(level, seconds);
+            }
+        }
+        data
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let mut entries = vec![("unlocked_levels".to_string(), self.unlocked_levels.to_string())];
+        let mut levels: Vec<&usize> = self.best_times.keys().collect();
+        levels.sort();
+        for level in levels {
+            entries.push((format!("best_time.{}", level), format!("{:.2}", self.best_times[level])));
+        }
+        KeyValueFile::save(Self::FILE, SAVE_VERSION, &entries)
+    }
+
+    /// Registra que se ganó un nivel: desbloquea el siguiente y guarda el tiempo si es récord.
+    /// Devuelve true si fue un nuevo mejor tiempo.
+    pub fn record_win(&mut self, level: usize, seconds: f32) -> bool {
+        self.unlocked_levels = self.unlocked_levels.max(level + 1);
+        let best = self.best_times.entry(level).or_insert(f32::INFINITY);
+        let record = seconds < *best;
+        if record {
+            *best = seconds;
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_and_values() {
+        let file = KeyValueFile::parse("version=1\n# comentario\n\n unlocked_levels = 2 \nbest_time.1=42.50\nbasura\n");
+        assert_eq!(file.version, 1);
+        assert_eq!(file.get::<usize>("unlocked_levels"), Some(2));
+        assert_eq!(file.get::<f32>("best_time.1"), Some(42.5));
+        assert_eq!(file.get::<f32>("missing"), None);
+        assert_eq!(file.values.len(), 2);
+    }
+
+    #[test]
+    fn missing_or_bad_version_is_zero() {
+        assert_eq!(KeyValueFile::parse("a=1").version, 0);
+        assert_eq!(KeyValueFile::parse("version=x\na=1").version, 0);
+        assert_eq!(KeyValueFile::parse("a=no es número").get::<u32>("a"), None);
+    }
+
+    #[test]
+    fn record_win_keeps_best_time_and_progress() {
+        let mut data = SaveData::new();
+        assert!(data.record_win(1, 50.0));
+        assert!(!data.record_win(1, 60.0));
+        assert!(data.record_win(1, 40.0));
+        assert_eq!(data.best_times[&1], 40.0);
+        assert_eq!(data.unlocked_levels, 2);
+        data.record_win(0, 10.0);
+        assert_eq!(data.unlocked_levels, 2);
+    }
+}
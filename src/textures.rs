@@ -1,89 +1,448 @@
 use raylib::prelude::*;
 use std::collections::HashMap;
+use image::ImageReader;
+use crate::caster::{Face, Intersect};
+use std::path::Path;
+use crate::manifest::{BlendMode, Frame, Layer, Manifest, Source, Target, TextureDef};
+use crate::procedural;
+use crate::maze::Directives;
+use crate::decals::{Decals, DEFAULT_DECAL_CHANCE};
+use crate::console::{Command, Console};
 
-pub struct TextureManager {
-    images: HashMap<char, Image>,
+/// Índice denso de una textura dentro del `TextureStore`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TextureId(u32);
+
+/// Cómo se muestrean las texturas al renderizar (opciones)
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Sampling {
+    pub mipmaps: bool,  // usar versiones reducidas cuando la textura se ve chica (evita el parpadeo)
+    pub bilinear: bool, // interpolar entre texels cuando se ve grande (si no, nearest)
 }
 
-impl TextureManager {
-    /// Carga texturas para '-', '|', '+' componiendo wall+graffiti, y define fallbacks.
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Result<Self, String> {
-        let mut images: HashMap<char, Image> = HashMap::new();
-        let mut textures: HashMap<char, Texture2D> = HashMap::new();
-
-        // Mapa de caracteres a archivos (base wall + overlay graffiti)
-        let defs: Vec<(char, &str, &str)> = vec![
-            ('-', "assets/wall1.png", "assets/graffiti1.png"),
-            ('|', "assets/wall2.png", "assets/graffiti2.png"),
-            ('+', "assets/wall3.png", "assets/graffiti3.png"),
-        ];
-
-        // Cargar y componer
-        for (ch, wall_path, graff_path) in defs {
-            let mut wall = Image::load_image(wall_path).map_err(|e| format!("{}: {}", wall_path, e))?;
-            let mut graf = Image::load_image(graff_path).map_err(|e| format!("{}: {}", graff_path, e))?;
-            let composed = compose_overlay(&mut wall, &mut graf)?;
-            let tex = rl
-                .load_texture_from_image(thread, &composed)
-                .map_err(|e| format!("load_texture_from_image: {}", e))?;
-            images.insert(ch, composed);
-            textures.insert(ch, tex);
+/// Textura decodificada en CPU: RGBA8 contiguo, fila por fila
+pub struct CpuTexture {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<u8>,
+    pow2: Option<(u32, u32, u32)>, // (máscara x, máscara y, shift de fila) si ambos lados son potencia de 2
+    mips: Vec<CpuTexture>,         // mitad, cuarto, ... hasta 1x1; vacío hasta que se piden
+}
+
+impl CpuTexture {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        let pow2 = (width.is_power_of_two() && height.is_power_of_two())
+            .then(|| (width - 1, height - 1, width.trailing_zeros()));
+        Self { width, height, pixels, pow2, mips: Vec::new() }
+    }
+
+    /// Decodifica cualquier formato que entienda `image` (png, webp, ...) a RGBA8
+    pub fn load(path: &str) -> Result<Self, String> {
+        let reader = ImageReader::open(path)
+            .map_err(|e| format!("open {}: {}", path, e))?
+            .with_guessed_format()
+            .map_err(|e| format!("format {}: {}", path, e))?;
+        let dynimg = reader.decode().map_err(|e| format!("decode {}: {}", path, e))?;
+        let rgba = dynimg.to_rgba8();
+        let (w, h) = rgba.dimensions();
+        Ok(Self::new(w, h, rgba.into_raw()))
+    }
+
+    /// Color en (x, y); fuera de rango la textura se repite. Con lados potencia de 2
+    /// el índice sale con máscaras y shift, sin divisiones.
+    #[inline]
+    pub fn texel(&self, x: u32, y: u32) -> Color {
+        let index = match self.pow2 {
+            Some((mask_x, mask_y, shift)) => (((y & mask_y) << shift) | (x & mask_x)) as usize,
+            None => ((y % self.height) * self.width + x % self.width) as usize,
+        };
+        match self.pixels.get(index * 4..index * 4 + 4) {
+            Some(p) => Color::new(p[0], p[1], p[2], p[3]),
+            None => Color::WHITE,
+        }
+    }
+
+    /// Color en (u, v) de 0 a 1 (se repite fuera de rango). `lod` es log2 de cuántos texels
+    /// caen en un píxel de pantalla: con mipmaps, 1 o más usa un nivel reducido.
+    pub fn sample(&self, u: f32, v: f32, lod: f32, sampling: Sampling) -> Color {
+        self.filter((u.rem_euclid(1.0), v.rem_euclid(1.0)), lod, sampling, true)
+    }
+
+    /// Como `sample`, pero sin repetir: (u, v) se limita a la textura y el bilineal no toma
+    /// texels del borde opuesto (frames de sprites, que van sueltos)
+    pub fn sample_clamped(&self, u: f32, v: f32, lod: f32, sampling: Sampling) -> Color {
+        self.filter((u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)), lod, sampling, false)
+    }
+
+    fn filter(&self, (u, v): (f32, f32), lod: f32, sampling: Sampling, wrap: bool) -> Color {
+        let level = if sampling.mipmaps && lod >= 1.0 { (lod as usize).min(self.mips.len()) } else { 0 };
+        let texture = if level == 0 { self } else { &self.mips[level - 1] };
+        let (w, h) = (texture.width, texture.height);
+        let u = u * w as f32;
+        let v = v * h as f32;
+        if !sampling.bilinear {
+            return texture.texel((u as u32).min(w - 1), (v as u32).min(h - 1));
         }
 
-        // x default se usa - si hay un simbolo no definido
-        if let Some(img) = images.get(&'-').cloned() {
-            let tex = rl
-                .load_texture_from_image(thread, &img)
-                .map_err(|e| format!("fallback texture load: {}", e))?;
-            images.insert('#', img);
-            textures.insert('#', tex);
+        // Bilineal: promedio de los 4 texels vecinos pesado por la distancia a sus centros
+        let (x, y) = if wrap {
+            ((u - 0.5).rem_euclid(w as f32), (v - 0.5).rem_euclid(h as f32))
+        } else {
+            ((u - 0.5).clamp(0.0, (w - 1) as f32), (v - 0.5).clamp(0.0, (h - 1) as f32))
+        };
+        let (x0, y0) = (x as u32, y as u32);
+        let (x1, y1) = if wrap { (x0 + 1, y0 + 1) } else { ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1)) };
+        let (fx, fy) = (x.fract(), y.fract());
+        blend(&[
+            (texture.texel(x0, y0), (1.0 - fx) * (1.0 - fy)),
+            (texture.texel(x1, y0), fx * (1.0 - fy)),
+            (texture.texel(x0, y1), (1.0 - fx) * fy),
+            (texture.texel(x1, y1), fx * fy),
+        ])
+    }
+
+    /// Genera la cadena de mipmaps (promedio de 2x2) si todavía no existe
+    fn build_mips(&mut self) {
+        if !self.mips.is_empty() { return; }
+        let mut current = self.half();
+        while let Some(level) = current {
+            current = level.half();
+            self.mips.push(level);
+        }
+    }
+
+    /// Versión a la mitad de tamaño; None si ya es 1x1
+    fn half(&self) -> Option<CpuTexture> {
+        if self.width == 1 && self.height == 1 { return None; }
+        let (w, h) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut pixels = vec![0u8; (w * h * 4) as usize];
+        for y in 0..h {
+            for x in 0..w {
+                let samples = [
+                    self.texel(x * 2, y * 2),
+                    self.texel((x * 2 + 1).min(self.width - 1), y * 2),
+                    self.texel(x * 2, (y * 2 + 1).min(self.height - 1)),
+                    self.texel((x * 2 + 1).min(self.width - 1), (y * 2 + 1).min(self.height - 1)),
+                ];
+                let c = blend(&samples.map(|c| (c, 0.25)));
+                let i = ((y * w + x) * 4) as usize;
+                pixels[i..i + 4].copy_from_slice(&[c.r, c.g, c.b, c.a]);
+            }
         }
+        Some(CpuTexture::new(w, h, pixels))
+    }
+}
+
+/// Promedio pesado de colores. El color se pesa además por el alpha, así los texels
+/// transparentes no tiñen los bordes (el alpha sí se promedia con los pesos solos).
+fn blend(samples: &[(Color, f32)]) -> Color {
+    let alpha: f32 = samples.iter().map(|(c, w)| c.a as f32 * w).sum();
+    if alpha <= 0.0 { return Color::new(0, 0, 0, 0); }
+    let channel = |f: fn(&Color) -> u8| (samples.iter().map(|(c, w)| f(c) as f32 * c.a as f32 * w).sum::<f32>() / alpha).round() as u8;
+    Color::new(channel(|c| c.r), channel(|c| c.g), channel(|c| c.b), alpha.round().min(255.0) as u8)
+}
+
+/// Todas las imágenes del juego (paredes y hojas de sprites), decodificadas una sola vez.
+/// Es de solo lectura al renderizar, así que los hilos de render la comparten.
+pub struct TextureStore {
+    textures: Vec<CpuTexture>,
+    sampling: Sampling,
+}
+
+impl TextureStore {
+    pub fn new() -> Self {
+        Self { textures: Vec::new(), sampling: Sampling::default() }
+    }
+
+    pub fn add(&mut self, mut texture: CpuTexture) -> TextureId {
+        if self.sampling.mipmaps { texture.build_mips(); }
+        self.textures.push(texture);
+        TextureId(self.textures.len() as u32 - 1)
+    }
+
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    /// Cambia el muestreo; los mipmaps se generan recién la primera vez que se activan
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        if sampling.mipmaps && !self.sampling.mipmaps {
+            self.textures.iter_mut().for_each(CpuTexture::build_mips);
+        }
+        self.sampling = sampling;
+    }
+
+    pub fn get(&self, id: TextureId) -> &CpuTexture {
+        &self.textures[id.0 as usize]
+    }
+}
+
+/// Manifiesto general de texturas de pared
+pub const TEXTURE_MANIFEST: &str = "assets/textures.txt";
+
+/// Manifiesto de respaldo si falta el general: todo procedural, sin imágenes
+const BUILTIN_MANIFEST: &str = "
+procedural concreto concrete
+procedural ladrillo bricks
+procedural azulejo tiles
+procedural espejo tiles 205,220,235 150,160,175
+procedural portal tiles 150,70,240 60,20,110
+char - concreto
+char | ladrillo
+char + azulejo
+char M espejo
+char O portal
+default concreto
+";
+
+/// Índice de una pared (textura quizás animada) dentro del `TextureManager`
+type WallId = usize;
 
-        Ok(Self { images })
+/// Textura de pared lista para muestrear: frames ya compuestos y cómo se mueven
+struct WallTexture {
+    frames: Vec<TextureId>,
+    fps: f32,
+    scroll: (f32, f32),
+}
+
+/// Asignaciones de un manifiesto ya cargadas en el store
+#[derive(Default)]
+struct Palette {
+    chars: HashMap<char, WallId>,
+    cells: HashMap<(usize, usize, Option<Face>), WallId>,
+    default: Option<WallId>,
+    decals: Vec<TextureId>,
+    decal_chance: Option<f32>,
+}
+
+impl Palette {
+    /// Pared para un impacto: cara de la celda, celda, símbolo y por último el default
+    fn lookup(&self, hit: &Intersect) -> Option<WallId> {
+        let by_cell = hit.cell.and_then(|(x, y)| {
+            self.cells.get(&(x, y, Some(hit.face))).or_else(|| self.cells.get(&(x, y, None)))
+        });
+        by_cell.or_else(|| self.chars.get(&hit.wall_type)).copied().or(self.default)
+    }
+}
+
+/// Clave de la caché de paredes: la definición con los f32 por sus bits (fps y scroll)
+type WallKey = (Vec<Frame>, Vec<Layer>, u32, (u32, u32));
+
+/// Texturas de pared según el manifiesto general y la paleta del nivel actual,
+/// guardadas en el store compartido
+pub struct TextureManager {
+    pub store: TextureStore,
+    walls: Vec<WallTexture>,
+    base: Palette,
+    level: Palette, // del `@palette` del nivel; tiene prioridad sobre la general
+    fallback: WallId,
+    composed: HashMap<(Frame, Vec<Layer>), TextureId>, // frames ya compuestos, para no repetirlos por nivel
+    wall_ids: HashMap<WallKey, WallId>,   // lo mismo para las paredes completas
+    time: f32,                            // segundos de juego, para animar las paredes
+    level_palette: Option<String>,        // archivo del `@palette` del nivel, para recargarlo
+    pub decals: Decals,
+}
+
+impl TextureManager {
+    /// Carga el manifiesto general (o el procedural de respaldo si no existe) componiendo
+    /// cada textura con sus capas
+    pub fn new() -> Result<Self, String> {
+        let mut store = TextureStore::new();
+        let decals = Decals::new(&mut store);
+        let mut manager = Self {
+            store,
+            walls: Vec::new(),
+            base: Palette::default(),
+            level: Palette::default(),
+            fallback: 0,
+            composed: HashMap::new(),
+            wall_ids: HashMap::new(),
+            time: 0.0,
+            level_palette: None,
+            decals,
+        };
+        manager.load_base()?;
+        Ok(manager)
     }
 
-    // Obtener el tamaño de la imagen
-    pub fn get_image_size(&self, ch: char) -> (u32, u32) {
-        if let Some(img) = self.images.get(&ch) {
-            (img.width as u32, img.height as u32)
-        } else if let Some(img) = self.images.get(&'#') {
-            (img.width as u32, img.height as u32)
+    fn load_base(&mut self) -> Result<(), String> {
+        let manifest = if Path::new(TEXTURE_MANIFEST).exists() {
+            Manifest::load(TEXTURE_MANIFEST)?
         } else {
-            (1, 1)
+            println!("[textures] no existe {}, se usan texturas procedurales", TEXTURE_MANIFEST);
+            Manifest::parse(BUILTIN_MANIFEST, "manifiesto interno")?
+        };
+        self.base = self.load_palette(&manifest)?;
+        // sin `default`, la primera textura asignada en el manifiesto (ya está en la caché)
+        self.fallback = match (self.base.default, manifest.mappings.first()) {
+            (Some(id), _) => id,
+            (None, Some((_, name))) => self.wall_cached(&manifest.textures[name])?,
+            (None, None) => return Err(format!("{}: no asigna ninguna textura", TEXTURE_MANIFEST)),
+        };
+        Ok(())
+    }
+
+    /// Vuelve a leer de disco el manifiesto general, la paleta del nivel y sus imágenes
+    /// (comando `reload textures`). Si algo falla se queda todo como estaba; las texturas
+    /// viejas siguen en el store, sin usarse, hasta cerrar el juego.
+    pub fn reload(&mut self) -> Result<(), String> {
+        let walls = std::mem::take(&mut self.walls);
+        let composed = std::mem::take(&mut self.composed);
+        let wall_ids = std::mem::take(&mut self.wall_ids);
+        let (base, level, fallback) = (std::mem::take(&mut self.base), std::mem::take(&mut self.level), self.fallback);
+        let result = self.load_base().and_then(|_| match self.level_palette.clone() {
+            Some(path) => Manifest::load(&path).and_then(|manifest| self.load_palette(&manifest)).map(|palette| self.level = palette),
+            None => Ok(()),
+        });
+        match result {
+            // los decals apuntan al pool viejo: se reparten de nuevo con la misma semilla
+            Ok(()) => {
+                let (pool, chance) = self.decal_pool();
+                self.decals.reseed(pool, chance);
+            }
+            Err(_) => {
+                (self.walls, self.composed, self.wall_ids) = (walls, composed, wall_ids);
+                (self.base, self.level, self.fallback) = (base, level, fallback);
+            }
         }
+        result
     }
 
-    // Devuelve el color en (tx, ty) del Image mapeado al simbolo
-    pub fn get_pixel_color_mut(&mut self, ch: char, tx: u32, ty: u32) -> Color {
-        let key = if self.images.contains_key(&ch) { ch } else { '#' };
-        if let Some(img) = self.images.get_mut(&key) {
-            let w = img.width.max(1) as u32;
-            let h = img.height.max(1) as u32;
-            let x = tx.min(w - 1) as i32;
-            let y = ty.min(h - 1) as i32;
-            return img.get_color(x, y);
+    fn load_palette(&mut self, manifest: &Manifest) -> Result<Palette, String> {
+        let mut palette = Palette { decal_chance: manifest.decal_chance, ..Palette::default() };
+        for frame in &manifest.decals {
+            palette.decals.push(self.compose_cached(frame, &[])?);
         }
-        Color::WHITE
-    }
-}
-
-// Metodo para poder tener wall de base y el graffiti encima (ambas son 256x256)
-fn compose_overlay(base: &mut Image, overlay: &mut Image) -> Result<Image, String> {
-    let bw = base.width as i32; let bh = base.height as i32;
-    if overlay.width != base.width || overlay.height != base.height {
-        return Err("wall.png y graffiti.png deben tener el mismo tamaño".into());
-    }
-    let mut out = Image::gen_image_color(bw, bh, Color::BLACK);
-    for y in 0..bh {
-        for x in 0..bw {
-            let bc = base.get_color(x, y);
-            let oc = overlay.get_color(x, y);
-            let a  = oc.a as u16; let ai = 255u16 - a;
-            let r = ((oc.r as u16 * a + bc.r as u16 * ai) / 255) as u8;
-            let g = ((oc.g as u16 * a + bc.g as u16 * ai) / 255) as u8;
-            let b = ((oc.b as u16 * a + bc.b as u16 * ai) / 255) as u8;
-            out.draw_pixel(x, y, Color::new(r, g, b, 255));
+        for (target, name) in &manifest.mappings {
+            let id = self.wall_cached(&manifest.textures[name])?;
+            match *target {
+                Target::Char(ch) => { palette.chars.insert(ch, id); }
+                Target::Cell(x, y, face) => { palette.cells.insert((x, y, face), id); }
+                Target::Default => palette.default = Some(id),
+            }
         }
+        Ok(palette)
     }
-    Ok(out)
-}
\ No newline at end of file
+
+    fn wall_cached(&mut self, def: &TextureDef) -> Result<WallId, String> {
+        let key = (def.frames.clone(), def.layers.clone(), def.fps.to_bits(), (def.scroll.0.to_bits(), def.scroll.1.to_bits()));
+        if let Some(&id) = self.wall_ids.get(&key) { return Ok(id); }
+        let frames = def.frames.iter()
+            .map(|frame| self.compose_cached(frame, &def.layers))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.walls.push(WallTexture { frames, fps: def.fps, scroll: def.scroll });
+        self.wall_ids.insert(key, self.walls.len() - 1);
+        Ok(self.walls.len() - 1)
+    }
+
+    fn compose_cached(&mut self, frame: &Frame, layers: &[Layer]) -> Result<TextureId, String> {
+        let key = (frame.clone(), layers.to_vec());
+        if let Some(&id) = self.composed.get(&key) { return Ok(id); }
+        let id = self.store.add(compose(frame, layers)?);
+        self.composed.insert(key, id);
+        Ok(id)
+    }
+
+    /// Prepara las paredes de un nivel: su paleta (`@palette archivo`; sin directiva se usa
+    /// solo la general) y el graffiti repartido con `decal_seed`
+    pub fn load_level(&mut self, directives: &Directives, decal_seed: u64) {
+        self.level = Palette::default();
+        self.time = 0.0;
+        self.level_palette = directives.iter().find(|(key, _)| key == "palette").map(|(_, path)| path.clone());
+        if let Some(path) = self.level_palette.clone() {
+            match Manifest::load(&path).and_then(|manifest| self.load_palette(&manifest)) {
+                Ok(palette) => self.level = palette,
+                Err(e) => println!("[textures] {}", e),
+            }
+        }
+        let (pool, chance) = self.decal_pool();
+        self.decals.reset(decal_seed, pool, chance);
+    }
+
+    /// Pool de graffiti y probabilidad: los de la paleta del nivel si trae, si no los generales
+    fn decal_pool(&self) -> (Vec<TextureId>, f32) {
+        let pool = if self.level.decals.is_empty() { &self.base.decals } else { &self.level.decals };
+        let chance = self.level.decal_chance.or(self.base.decal_chance).unwrap_or(DEFAULT_DECAL_CHANCE);
+        (pool.clone(), chance)
+    }
+
+    /// Avanza las animaciones de las paredes (solo corre mientras se juega)
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// Textura de pared para un impacto en el frame actual, con su desplazamiento (u, v)
+    /// en fracción de la textura; se busca una vez por columna, no por píxel
+    pub fn wall(&self, hit: &Intersect) -> (&CpuTexture, (f32, f32)) {
+        let id = self.level.lookup(hit).or_else(|| self.base.lookup(hit)).unwrap_or(self.fallback);
+        let wall = &self.walls[id];
+        let frame = if wall.fps > 0.0 { (self.time * wall.fps) as usize % wall.frames.len() } else { 0 };
+        let offset = ((wall.scroll.0 * self.time).rem_euclid(1.0), (wall.scroll.1 * self.time).rem_euclid(1.0));
+        (self.store.get(wall.frames[frame]), offset)
+    }
+}
+
+fn load_source(source: &Source) -> Result<CpuTexture, String> {
+    match source {
+        Source::File(path) => CpuTexture::load(path),
+        Source::Procedural(pattern, primary, secondary) => Ok(procedural::generate(*pattern, *primary, *secondary)),
+    }
+}
+
+/// Compone un frame con sus capas; las capas de otro tamaño se estiran al del frame
+fn compose(frame: &Frame, layers: &[Layer]) -> Result<CpuTexture, String> {
+    let base = load_source(&frame.source)?;
+    let mut out = base.pixels.clone();
+    for pixel in out.chunks_exact_mut(4) {
+        tint_pixel(pixel, frame.tint);
+    }
+    for layer in layers {
+        let image = CpuTexture::load(&layer.path)?;
+        for (i, pixel) in out.chunks_exact_mut(4).enumerate() {
+            let x = (i as u32 % base.width) * image.width / base.width;
+            let y = (i as u32 / base.width) * image.height / base.height;
+            let c = image.texel(x, y);
+            let mut src = [c.r, c.g, c.b, c.a];
+            tint_pixel(&mut src, layer.tint);
+            blend_pixel(pixel, &src, layer.blend);
+        }
+    }
+    Ok(CpuTexture::new(base.width, base.height, out))
+}
+fn tint_pixel(pixel: &mut [u8], tint: Color) {
+    for (value, t) in pixel.iter_mut().zip([tint.r, tint.g, tint.b, tint.a]) {
+        *value = (*value as u16 * t as u16 / 255) as u8;
+    }
+}
+
+// Mezcla `src` sobre `dst` según el modo, pesado por el alpha de `src`; el resultado es opaco
+fn blend_pixel(dst: &mut [u8], src: &[u8], mode: BlendMode) {
+    let a = src[3] as u16; let ai = 255u16 - a;
+    for c in 0..3 {
+        let (d, s) = (dst[c] as u16, src[c] as u16);
+        let blended = match mode {
+            BlendMode::Alpha => s,
+            BlendMode::Add => (d + s).min(255),
+            BlendMode::Multiply => d * s / 255,
+            BlendMode::Screen => 255 - (255 - d) * (255 - s) / 255,
+        };
+        dst[c] = ((blended * a + d * ai) / 255) as u8;
+    }
+    dst[3] = 255;
+}
+
+/// Comandos de consola de las texturas
+pub fn register_console_commands(console: &mut Console) {
+    console.register(Command {
+        name: "reload",
+        usage: "reload textures",
+        help: "vuelve a leer el manifiesto y las imagenes de las paredes",
+        args: &["textures"],
+        run: |ctx, args| match args {
+            ["textures"] => ctx.texman.reload().map(|_| "texturas recargadas".to_string()),
+            _ => Err("uso: reload textures".to_string()),
+        },
+    });
+}